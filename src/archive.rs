@@ -1,5 +1,8 @@
+use crate::filter::IgnoreFilter;
+use bzip2::read::BzDecoder;
+use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
-use flate2::Compression;
+use sha2::{Digest, Sha256};
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::{Seek, Write};
@@ -7,7 +10,9 @@ use std::iter::Iterator;
 use std::path::{Path, PathBuf};
 use std::{fs, io};
 use walkdir::{DirEntry, WalkDir};
+use xz2::read::XzDecoder;
 use zip::write::SimpleFileOptions;
+use zip::CompressionMethod;
 
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
 pub enum ArchiveMode {
@@ -24,34 +29,220 @@ impl ArchiveMode {
         src: impl AsRef<Path>,
         to: impl AsRef<Path>,
     ) -> anyhow::Result<Option<PathBuf>> {
+        self.make_archive_with(src, to, &ArchiveOptions::default())
+    }
+
+    /// Create either a tar or zip file of the provided source path using the
+    /// compression configured in `options`.
+    ///
+    /// The extension of the created archive is appended to `to`, depending on
+    /// the mode and compression, e.g. `.zip` or `.tar.gz`.
+    ///
+    /// File contents are streamed into the archive, so this never holds a
+    /// whole file in memory. This does blocking IO and should be run on a
+    /// blocking thread when called from async code.
+    pub fn make_archive_with(
+        &self,
+        src: impl AsRef<Path>,
+        to: impl AsRef<Path>,
+        options: &ArchiveOptions,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        self.archive(src.as_ref(), to.as_ref(), options, None)
+    }
+
+    /// Same as [`ArchiveMode::make_archive_with`] but skips all entries that
+    /// are ignored by the `filter`.
+    pub fn make_archive_filtered(
+        &self,
+        src: impl AsRef<Path>,
+        to: impl AsRef<Path>,
+        options: &ArchiveOptions,
+        filter: &IgnoreFilter,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        self.archive(src.as_ref(), to.as_ref(), options, Some(filter))
+    }
+
+    /// The name of the archive that is created for a directory with this
+    /// name, `None` if directories are not archived.
+    pub fn archive_name(&self, name: &str, options: &ArchiveOptions) -> Option<String> {
         match self {
-            ArchiveMode::Tar => {
-                let to = PathBuf::from(format!("{}.tar.gz", to.as_ref().display()));
+            ArchiveMode::Tar => Some(format!("{}{}", name, options.compression.tar_extension())),
+            ArchiveMode::Zip => Some(format!("{}.zip", name)),
+            ArchiveMode::Skip => None,
+        }
+    }
+
+    fn archive(
+        &self,
+        src: &Path,
+        to: &Path,
+        options: &ArchiveOptions,
+        filter: Option<&IgnoreFilter>,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        let to = match to.to_str() {
+            Some(to) => self.archive_name(to, options).map(PathBuf::from),
+            None => return Err(anyhow::anyhow!("Archive path is not valid unicode")),
+        };
+        match (self, to) {
+            (ArchiveMode::Tar, Some(to)) => {
                 let file = File::create(&to)?;
-                make_tarball(src, file)?;
+                make_tarball(src, file, options, filter)?;
                 Ok(Some(to))
             }
-            ArchiveMode::Zip => {
-                let src = src.as_ref();
-                let to = PathBuf::from(format!("{}.zip", to.as_ref().display()));
+            (ArchiveMode::Zip, Some(to)) => {
                 let file = File::create(&to)?;
-                let walkdir = WalkDir::new(src);
-                let it = walkdir.into_iter();
+                let mut it = options.entries(src, filter).filter_map(|e| e.ok());
 
-                zip_dir(&mut it.filter_map(|e| e.ok()), src, file)?;
+                zip_dir(&mut it, src, file, options)?;
                 Ok(Some(to))
             }
-            ArchiveMode::Skip => Ok(None),
+            _ => Ok(None),
         }
     }
 }
 
+/// The compression method used when creating archives.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum ArchiveCompression {
+    /// No compression at all
+    Stored,
+    #[default]
+    Deflate,
+    Zstd,
+    Bzip2,
+}
+
+impl ArchiveCompression {
+    /// The extension of a tarball compressed with this method.
+    fn tar_extension(&self) -> &'static str {
+        match self {
+            ArchiveCompression::Stored => ".tar",
+            ArchiveCompression::Deflate => ".tar.gz",
+            ArchiveCompression::Zstd => ".tar.zst",
+            ArchiveCompression::Bzip2 => ".tar.bz2",
+        }
+    }
+
+    fn zip_method(&self) -> CompressionMethod {
+        match self {
+            ArchiveCompression::Stored => CompressionMethod::Stored,
+            ArchiveCompression::Deflate => CompressionMethod::Deflated,
+            ArchiveCompression::Zstd => CompressionMethod::Zstd,
+            ArchiveCompression::Bzip2 => CompressionMethod::Bzip2,
+        }
+    }
+}
+
+/// Options to configure how [`ArchiveMode::make_archive_with`] creates
+/// archives.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct ArchiveOptions {
+    /// The compression method to use
+    pub compression: ArchiveCompression,
+    /// The compression level, if `None` the default level of the compression
+    /// method is used. Out of range levels are clamped to the valid range of
+    /// the method.
+    pub level: Option<i64>,
+    /// Whether to create reproducible archives.
+    ///
+    /// Entries are sorted by name, timestamps are fixed, owner fields are
+    /// cleared and permissions are normalized to `0o755` for directories and
+    /// executables and `0o644` for everything else. Archiving the same content
+    /// twice then results in byte-identical archives, see [`sha256_digest`].
+    pub deterministic: bool,
+}
+
+impl ArchiveOptions {
+    pub fn compression(mut self, compression: ArchiveCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn level(mut self, level: i64) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// All entries of `src`, including `src` itself, that are not ignored by
+    /// the `filter`.
+    fn entries<'a>(
+        &self,
+        src: &Path,
+        filter: Option<&'a IgnoreFilter>,
+    ) -> impl Iterator<Item = walkdir::Result<DirEntry>> + 'a {
+        let mut walkdir = WalkDir::new(src);
+        if self.deterministic {
+            walkdir = walkdir.sort_by_file_name();
+        }
+        walkdir.into_iter().filter_entry(move |e| {
+            filter.is_none_or(|f| !f.is_ignored(e.path(), e.file_type().is_dir()))
+        })
+    }
+
+    /// The permissions to store for an entry with the given metadata.
+    fn permissions(&self, meta: &fs::Metadata) -> u32 {
+        let mode = unix_permissions(meta);
+        if !self.deterministic {
+            mode
+        } else if meta.is_dir() || mode & 0o100 == 0o100 {
+            0o755
+        } else {
+            0o644
+        }
+    }
+
+    fn zip_options(&self) -> SimpleFileOptions {
+        let level = match self.compression {
+            ArchiveCompression::Stored => None,
+            ArchiveCompression::Deflate => self.level.map(|l| l.clamp(0, 9)),
+            ArchiveCompression::Zstd => self.level.map(|l| l.clamp(1, 22)),
+            ArchiveCompression::Bzip2 => self.level.map(|l| l.clamp(1, 9)),
+        };
+        let options = SimpleFileOptions::default()
+            .compression_method(self.compression.zip_method())
+            .compression_level(level);
+        if self.deterministic {
+            // the earliest timestamp zip can represent, 1980-01-01 00:00:00
+            options.last_modified_time(zip::DateTime::default())
+        } else {
+            options
+        }
+    }
+}
+
+/// Compute the hex encoded SHA-256 digest of the file's content.
+///
+/// Together with [`ArchiveOptions::deterministic`] this can be used to detect
+/// whether an archive changed since it was last uploaded.
+pub fn sha256_digest(file: impl AsRef<Path>) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(file)?, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
 /// unzip file into location of `to`
 pub fn unzip(file: impl AsRef<Path>, to: impl AsRef<Path>) -> anyhow::Result<()> {
+    unzip_files(file, to)?;
+    Ok(())
+}
+
+/// unzip file into location of `to` and return the paths of all extracted
+/// files
+fn unzip_files(file: impl AsRef<Path>, to: impl AsRef<Path>) -> anyhow::Result<Vec<PathBuf>> {
     let file = file.as_ref();
     let to = to.as_ref();
     let file = fs::File::open(file)?;
     let mut archive = zip::ZipArchive::new(file)?;
+    let mut files = Vec::with_capacity(archive.len());
 
     for i in 0..archive.len() {
         let mut file = archive.by_index(i)?;
@@ -67,6 +258,7 @@ pub fn unzip(file: impl AsRef<Path>, to: impl AsRef<Path>) -> anyhow::Result<()>
             }
             let mut outfile = fs::File::create(&outpath)?;
             io::copy(&mut file, &mut outfile)?;
+            files.push(outpath.clone());
         }
 
         // Get and Set permissions
@@ -80,22 +272,298 @@ pub fn unzip(file: impl AsRef<Path>, to: impl AsRef<Path>) -> anyhow::Result<()>
         }
     }
 
-    Ok(())
+    Ok(files)
+}
+
+/// The archive and compression formats that can be extracted with [`extract`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarBz2,
+    TarXz,
+    TarZst,
+    /// A single gzip compressed file
+    Gz,
+    /// A single bzip2 compressed file
+    Bz2,
+    /// A single xz compressed file
+    Xz,
+    /// A single zstd compressed file
+    Zst,
+}
+
+impl ArchiveFormat {
+    const ZIP_MAGIC: &'static [u8] = b"PK\x03\x04";
+    const EMPTY_ZIP_MAGIC: &'static [u8] = b"PK\x05\x06";
+    const GZ_MAGIC: &'static [u8] = &[0x1f, 0x8b];
+    const BZ2_MAGIC: &'static [u8] = b"BZh";
+    const XZ_MAGIC: &'static [u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+    const ZST_MAGIC: &'static [u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+    /// Detect the format of the file by sniffing its magic bytes.
+    ///
+    /// Compressed files are additionally inspected after decompressing their
+    /// first block to tell a compressed tarball apart from a single compressed
+    /// file.
+    ///
+    /// Returns `None` if the file is not a supported archive.
+    pub fn detect(file: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        let file = file.as_ref();
+        let mut magic = Vec::with_capacity(TAR_BLOCK_SIZE);
+        File::open(file)?
+            .take(TAR_BLOCK_SIZE as u64)
+            .read_to_end(&mut magic)?;
+
+        let format =
+            if magic.starts_with(Self::ZIP_MAGIC) || magic.starts_with(Self::EMPTY_ZIP_MAGIC) {
+                ArchiveFormat::Zip
+            } else if magic.starts_with(Self::GZ_MAGIC) {
+                if is_tar(GzDecoder::new(File::open(file)?)) {
+                    ArchiveFormat::TarGz
+                } else {
+                    ArchiveFormat::Gz
+                }
+            } else if magic.starts_with(Self::BZ2_MAGIC) {
+                if is_tar(BzDecoder::new(File::open(file)?)) {
+                    ArchiveFormat::TarBz2
+                } else {
+                    ArchiveFormat::Bz2
+                }
+            } else if magic.starts_with(Self::XZ_MAGIC) {
+                if is_tar(XzDecoder::new(File::open(file)?)) {
+                    ArchiveFormat::TarXz
+                } else {
+                    ArchiveFormat::Xz
+                }
+            } else if magic.starts_with(Self::ZST_MAGIC) {
+                if is_tar(zstd::Decoder::new(File::open(file)?)?) {
+                    ArchiveFormat::TarZst
+                } else {
+                    ArchiveFormat::Zst
+                }
+            } else if has_tar_magic(&magic) {
+                ArchiveFormat::Tar
+            } else {
+                return Ok(None);
+            };
+        Ok(Some(format))
+    }
+
+    /// File extensions of archives that are extracted recursively.
+    pub const EXTENSIONS: &'static [&'static str] = &[
+        "zip", "tar", "tgz", "tbz2", "txz", "tzst", "gz", "bz2", "xz", "zst",
+    ];
+
+    /// Whether the file name ends with one of the [`ArchiveFormat::EXTENSIONS`].
+    ///
+    /// Zip containers like `.xlsx`, `.docx` or `.npz` files have the same
+    /// magic bytes as zip archives but are regular files.
+    pub fn has_archive_extension(file: impl AsRef<Path>) -> bool {
+        file.as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| Self::EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+    }
+
+    /// Whether this is a compressed single file rather than an archive of
+    /// multiple files.
+    pub fn is_single_file(&self) -> bool {
+        matches!(
+            self,
+            ArchiveFormat::Gz | ArchiveFormat::Bz2 | ArchiveFormat::Xz | ArchiveFormat::Zst
+        )
+    }
+}
+
+/// Size of a tar header block, the `ustar` magic is located at offset 257
+const TAR_BLOCK_SIZE: usize = 512;
+
+fn has_tar_magic(block: &[u8]) -> bool {
+    block.len() >= 262 && &block[257..262] == b"ustar"
+}
+
+/// Whether the decompressed content starts with a tar header.
+fn is_tar<R: Read>(reader: R) -> bool {
+    let mut block = Vec::with_capacity(TAR_BLOCK_SIZE);
+    reader
+        .take(TAR_BLOCK_SIZE as u64)
+        .read_to_end(&mut block)
+        .map(|_| has_tar_magic(&block))
+        .unwrap_or_default()
+}
+
+/// Options to configure how [`extract`] unpacks archives.
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    /// How many levels of nested archives should be extracted as well, `0`
+    /// only extracts the archive itself.
+    pub max_depth: usize,
+    /// Whether nested archives are deleted after they were extracted.
+    pub remove_nested: bool,
+}
+
+impl ExtractOptions {
+    /// Also extract archives that are contained in the archive up to `depth`
+    /// levels deep.
+    ///
+    /// Only nested files with an archive extension are extracted, see
+    /// [`ArchiveFormat::has_archive_extension`].
+    pub fn recursive(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn remove_nested(mut self, remove_nested: bool) -> Self {
+        self.remove_nested = remove_nested;
+        self
+    }
 }
 
+/// Extract the archive `file` into the directory `to` and return the paths of
+/// all extracted files.
+///
+/// The format is detected from the file's content, see
+/// [`ArchiveFormat::detect`]. Single compressed files are decompressed into
+/// `to`, with the compression extension removed from their name.
+///
+/// Nested archives are extracted into the directory they were found in, if
+/// enabled via [`ExtractOptions::recursive`].
+///
+/// Example
+///
+/// ```no_run
+/// use kaggle::archive::{self, ExtractOptions};
+///
+/// let files = archive::extract(
+///     "dataset.zip",
+///     "./dataset",
+///     &ExtractOptions::default().recursive(2),
+/// )
+/// .unwrap();
+/// ```
+pub fn extract(
+    file: impl AsRef<Path>,
+    to: impl AsRef<Path>,
+    opts: &ExtractOptions,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let file = file.as_ref();
+    let format = ArchiveFormat::detect(file)?
+        .ok_or_else(|| anyhow::anyhow!("{} is not a supported archive", file.display()))?;
+    extract_nested(file, format, to.as_ref(), opts, 0)
+}
+
+fn extract_nested(
+    file: &Path,
+    format: ArchiveFormat,
+    to: &Path,
+    opts: &ExtractOptions,
+    depth: usize,
+) -> anyhow::Result<Vec<PathBuf>> {
+    fs::create_dir_all(to)?;
+    let extracted = extract_format(file, format, to)?;
+    if depth >= opts.max_depth {
+        return Ok(extracted);
+    }
+
+    let mut files = Vec::with_capacity(extracted.len());
+    for path in extracted {
+        let nested = if ArchiveFormat::has_archive_extension(&path) {
+            ArchiveFormat::detect(&path)?
+        } else {
+            None
+        };
+        let nested = match nested {
+            Some(nested) => nested,
+            None => {
+                files.push(path);
+                continue;
+            }
+        };
+        let parent = path.parent().unwrap_or(to).to_path_buf();
+        files.extend(extract_nested(&path, nested, &parent, opts, depth + 1)?);
+        if opts.remove_nested {
+            fs::remove_file(&path)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+fn extract_format(file: &Path, format: ArchiveFormat, to: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let reader = || File::open(file).map(io::BufReader::new);
+    match format {
+        ArchiveFormat::Zip => unzip_files(file, to),
+        ArchiveFormat::Tar => untar(reader()?, to),
+        ArchiveFormat::TarGz => untar(GzDecoder::new(reader()?), to),
+        ArchiveFormat::TarBz2 => untar(BzDecoder::new(reader()?), to),
+        ArchiveFormat::TarXz => untar(XzDecoder::new(reader()?), to),
+        ArchiveFormat::TarZst => untar(zstd::Decoder::new(reader()?)?, to),
+        ArchiveFormat::Gz => decompress(GzDecoder::new(reader()?), file, to),
+        ArchiveFormat::Bz2 => decompress(BzDecoder::new(reader()?), file, to),
+        ArchiveFormat::Xz => decompress(XzDecoder::new(reader()?), file, to),
+        ArchiveFormat::Zst => decompress(zstd::Decoder::new(reader()?)?, file, to),
+    }
+}
+
+/// Unpack all entries of the tar archive into `to`, entries that would be
+/// placed outside of `to` are skipped.
+fn untar<R: Read>(reader: R, to: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let outpath = to.join(entry.path()?);
+        let is_file = entry.header().entry_type().is_file();
+        if entry.unpack_in(to)? && is_file {
+            files.push(outpath);
+        }
+    }
+    Ok(files)
+}
+
+/// Decompress a single compressed file into `to`.
+fn decompress<R: Read>(mut reader: R, file: &Path, to: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let name = decompressed_file_name(file)?;
+    let outpath = to.join(name);
+    let mut outfile = File::create(&outpath)?;
+    io::copy(&mut reader, &mut outfile)?;
+    Ok(vec![outpath])
+}
+
+/// The name of the decompressed file, `data.csv.gz` becomes `data.csv`.
+fn decompressed_file_name(file: &Path) -> anyhow::Result<PathBuf> {
+    let name = file
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("File path terminates in `..`"))?;
+    let ext = file.extension().and_then(|ext| ext.to_str());
+    match (file.file_stem(), ext) {
+        (Some(stem), Some("gz" | "bz2" | "xz" | "zst")) => Ok(PathBuf::from(stem)),
+        (Some(stem), Some("tgz" | "tbz2" | "txz" | "tzst")) => {
+            Ok(Path::new(stem).with_extension("tar"))
+        }
+        _ => Ok(PathBuf::from(format!("{}.out", name.to_string_lossy()))),
+    }
+}
+
+/// Files of at least this size need zip64 extensions
+const ZIP64_THRESHOLD: u64 = u32::MAX as u64;
+
 fn zip_dir<T>(
     it: &mut impl Iterator<Item = DirEntry>,
     prefix: impl AsRef<Path>,
     writer: T,
+    archive_options: &ArchiveOptions,
 ) -> anyhow::Result<()>
 where
     T: Write + Seek,
 {
     let prefix = prefix.as_ref();
     let mut zip = zip::ZipWriter::new(writer);
-    let options = SimpleFileOptions::default().unix_permissions(0o755);
+    let options = archive_options.zip_options();
 
-    let mut buffer = Vec::new();
     for entry in it {
         let path = entry.path();
         let name = path.strip_prefix(prefix)?;
@@ -103,25 +571,356 @@ where
         // Write file or directory explicitly
         // Some unzip tools unzip files with directory paths correctly, some do not!
         if path.is_file() {
-            zip.start_file_from_path(name, options)?;
             let mut f = File::open(path)?;
-
-            f.read_to_end(&mut buffer)?;
-            zip.write_all(&buffer)?;
-            buffer.clear();
+            let meta = f.metadata()?;
+            zip.start_file_from_path(
+                name,
+                options
+                    .large_file(meta.len() >= ZIP64_THRESHOLD)
+                    .unix_permissions(archive_options.permissions(&meta)),
+            )?;
+            io::copy(&mut f, &mut zip)?;
         } else if !name.as_os_str().is_empty() {
             // Only if not root! Avoids path spec / warning
             // and mapname conversion failed error on unzip
-            zip.add_directory_from_path(name, options)?;
+            zip.add_directory_from_path(
+                name,
+                options.unix_permissions(archive_options.permissions(&entry.metadata()?)),
+            )?;
         }
     }
     zip.finish()?;
     Ok(())
 }
 
-fn make_tarball<T: Write>(src: impl AsRef<Path>, writer: T) -> anyhow::Result<()> {
-    let enc = GzEncoder::new(writer, Compression::default());
-    let mut tar = tar::Builder::new(enc);
-    tar.append_dir_all(".", src)?;
+#[cfg(unix)]
+fn unix_permissions(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn unix_permissions(meta: &fs::Metadata) -> u32 {
+    match (meta.is_dir(), meta.permissions().readonly()) {
+        (true, _) => 0o755,
+        (false, true) => 0o444,
+        (false, false) => 0o644,
+    }
+}
+
+fn make_tarball<T: Write>(
+    src: &Path,
+    writer: T,
+    options: &ArchiveOptions,
+    filter: Option<&IgnoreFilter>,
+) -> anyhow::Result<()> {
+    let level = options.level;
+    match options.compression {
+        ArchiveCompression::Stored => {
+            append_tar(src, writer, options, filter)?;
+        }
+        ArchiveCompression::Deflate => {
+            let level = level
+                .map(|l| flate2::Compression::new(l.clamp(0, 9) as u32))
+                .unwrap_or_default();
+            append_tar(src, GzEncoder::new(writer, level), options, filter)?.finish()?;
+        }
+        ArchiveCompression::Zstd => {
+            let level = level.map(|l| l.clamp(1, 22) as i32).unwrap_or_default();
+            append_tar(src, zstd::Encoder::new(writer, level)?, options, filter)?.finish()?;
+        }
+        ArchiveCompression::Bzip2 => {
+            let level = level
+                .map(|l| bzip2::Compression::new(l.clamp(1, 9) as u32))
+                .unwrap_or_default();
+            append_tar(
+                src,
+                bzip2::write::BzEncoder::new(writer, level),
+                options,
+                filter,
+            )?
+            .finish()?;
+        }
+    }
     Ok(())
 }
+
+/// Write all files and directories of `src` into a tarball, file contents are
+/// streamed from disk.
+fn append_tar<T: Write>(
+    src: &Path,
+    writer: T,
+    options: &ArchiveOptions,
+    filter: Option<&IgnoreFilter>,
+) -> anyhow::Result<T> {
+    let mut tar = tar::Builder::new(writer);
+    if options.deterministic {
+        tar.mode(tar::HeaderMode::Deterministic);
+    }
+    for entry in options.entries(src, filter) {
+        let entry = entry?;
+        if entry.depth() == 0 {
+            continue;
+        }
+        let path = entry.path();
+        let name = path.strip_prefix(src)?;
+        if path.is_dir() {
+            tar.append_dir(name, path)?;
+        } else {
+            tar.append_path_with_name(path, name)?;
+        }
+    }
+    Ok(tar.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn gzip(content: &[u8], to: &Path) {
+        let mut enc = GzEncoder::new(File::create(to).unwrap(), Default::default());
+        enc.write_all(content).unwrap();
+        enc.finish().unwrap();
+    }
+
+    #[test]
+    fn detect_and_extract_single_gz() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("data.csv.gz");
+        gzip(b"a,b\n1,2\n", &file);
+
+        assert_eq!(
+            ArchiveFormat::detect(&file).unwrap(),
+            Some(ArchiveFormat::Gz)
+        );
+
+        let out = dir.path().join("out");
+        let files = extract(&file, &out, &ExtractOptions::default()).unwrap();
+        assert_eq!(files, vec![out.join("data.csv")]);
+        assert_eq!(fs::read(&files[0]).unwrap(), b"a,b\n1,2\n");
+    }
+
+    #[test]
+    fn extract_nested_archives() {
+        let dir = TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        gzip(b"nested", &src.join("inner.txt.gz"));
+
+        let archive = ArchiveMode::Tar
+            .make_archive(&src, dir.path().join("outer"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            ArchiveFormat::detect(&archive).unwrap(),
+            Some(ArchiveFormat::TarGz)
+        );
+
+        let out = dir.path().join("out");
+        let files = extract(&archive, &out, &ExtractOptions::default()).unwrap();
+        assert_eq!(files, vec![out.join("inner.txt.gz")]);
+
+        let out = dir.path().join("out-recursive");
+        let files = extract(
+            &archive,
+            &out,
+            &ExtractOptions::default().recursive(1).remove_nested(true),
+        )
+        .unwrap();
+        assert_eq!(files, vec![out.join("inner.txt")]);
+        assert_eq!(fs::read(&files[0]).unwrap(), b"nested");
+        assert!(!out.join("inner.txt.gz").exists());
+    }
+
+    #[test]
+    fn nested_zip_containers_stay_intact() {
+        let dir = TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        let book = dir.path().join("book");
+        fs::create_dir_all(src.join("sheets")).unwrap();
+        fs::create_dir_all(&book).unwrap();
+        fs::write(book.join("workbook.xml"), "<workbook/>").unwrap();
+        let xlsx = ArchiveMode::Zip
+            .make_archive(&book, dir.path().join("book"))
+            .unwrap()
+            .unwrap();
+        fs::rename(&xlsx, src.join("sheets/book.xlsx")).unwrap();
+        assert_eq!(
+            ArchiveFormat::detect(src.join("sheets/book.xlsx")).unwrap(),
+            Some(ArchiveFormat::Zip)
+        );
+
+        let archive = ArchiveMode::Tar
+            .make_archive(&src, dir.path().join("outer"))
+            .unwrap()
+            .unwrap();
+        let out = dir.path().join("out");
+        let files = extract(
+            &archive,
+            &out,
+            &ExtractOptions::default().recursive(2).remove_nested(true),
+        )
+        .unwrap();
+        assert_eq!(files, vec![out.join("sheets/book.xlsx")]);
+        assert_eq!(
+            fs::read(&files[0]).unwrap(),
+            fs::read(src.join("sheets/book.xlsx")).unwrap()
+        );
+    }
+
+    #[test]
+    fn zip_preserves_content_and_permissions() {
+        let dir = TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("nested")).unwrap();
+        let file = src.join("nested/data.csv");
+        fs::write(&file, "a,b\n1,2\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&file, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+
+        for compression in &[
+            ArchiveCompression::Stored,
+            ArchiveCompression::Deflate,
+            ArchiveCompression::Zstd,
+            ArchiveCompression::Bzip2,
+        ] {
+            let options = ArchiveOptions::default().compression(*compression).level(3);
+            let archive = ArchiveMode::Zip
+                .make_archive_with(&src, dir.path().join("data"), &options)
+                .unwrap()
+                .unwrap();
+            let out = dir.path().join("out");
+            let files = extract(&archive, &out, &ExtractOptions::default()).unwrap();
+            assert_eq!(files, vec![out.join("nested/data.csv")]);
+            assert_eq!(fs::read(&files[0]).unwrap(), b"a,b\n1,2\n");
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mode = fs::metadata(&files[0]).unwrap().permissions().mode();
+                assert_eq!(mode & 0o777, 0o640);
+            }
+            fs::remove_dir_all(out).unwrap();
+        }
+    }
+
+    #[test]
+    fn tar_extension_by_compression() {
+        let dir = TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("data.txt"), "content").unwrap();
+
+        let archive = ArchiveMode::Tar
+            .make_archive_with(
+                &src,
+                dir.path().join("data"),
+                &ArchiveOptions::default().compression(ArchiveCompression::Stored),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(archive, dir.path().join("data.tar"));
+        assert_eq!(
+            ArchiveFormat::detect(&archive).unwrap(),
+            Some(ArchiveFormat::Tar)
+        );
+
+        let archive = ArchiveMode::Tar
+            .make_archive_with(
+                &src,
+                dir.path().join("data"),
+                &ArchiveOptions::default().compression(ArchiveCompression::Bzip2),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(archive, dir.path().join("data.tar.bz2"));
+        assert_eq!(
+            ArchiveFormat::detect(&archive).unwrap(),
+            Some(ArchiveFormat::TarBz2)
+        );
+    }
+
+    #[test]
+    fn tar_zst_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("nested")).unwrap();
+        fs::write(src.join("nested/data.csv"), "a,b\n1,2\n").unwrap();
+
+        let archive = ArchiveMode::Tar
+            .make_archive_with(
+                &src,
+                dir.path().join("data"),
+                &ArchiveOptions::default().compression(ArchiveCompression::Zstd),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(archive, dir.path().join("data.tar.zst"));
+        assert_eq!(
+            ArchiveFormat::detect(&archive).unwrap(),
+            Some(ArchiveFormat::TarZst)
+        );
+        let out = dir.path().join("out");
+        let files = extract(&archive, &out, &ExtractOptions::default()).unwrap();
+        assert_eq!(files, vec![out.join("nested/data.csv")]);
+        assert_eq!(fs::read(&files[0]).unwrap(), b"a,b\n1,2\n");
+
+        let single = dir.path().join("readings.csv.zst");
+        fs::write(
+            &single,
+            zstd::encode_all(&b"station,temp\n"[..], 3).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            ArchiveFormat::detect(&single).unwrap(),
+            Some(ArchiveFormat::Zst)
+        );
+        let files = extract(&single, &out, &ExtractOptions::default()).unwrap();
+        assert_eq!(files, vec![out.join("readings.csv")]);
+        assert_eq!(fs::read(&files[0]).unwrap(), b"station,temp\n");
+    }
+
+    #[test]
+    fn deterministic_archives() {
+        let dir = TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("b")).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        fs::write(src.join("b/c.txt"), "c").unwrap();
+
+        let options = ArchiveOptions::default().deterministic(true);
+        for mode in &[ArchiveMode::Zip, ArchiveMode::Tar] {
+            let first = mode
+                .make_archive_with(&src, dir.path().join("first"), &options)
+                .unwrap()
+                .unwrap();
+
+            // touch the files to change their modification time
+            std::thread::sleep(std::time::Duration::from_millis(1100));
+            fs::write(src.join("a.txt"), "a").unwrap();
+            fs::write(src.join("b/c.txt"), "c").unwrap();
+
+            let second = mode
+                .make_archive_with(&src, dir.path().join("second"), &options)
+                .unwrap()
+                .unwrap();
+            assert_eq!(fs::read(&first).unwrap(), fs::read(&second).unwrap());
+            assert_eq!(
+                sha256_digest(&first).unwrap(),
+                sha256_digest(&second).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn detect_unsupported() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("plain.txt");
+        fs::write(&file, "no archive").unwrap();
+        assert_eq!(ArchiveFormat::detect(&file).unwrap(), None);
+        assert!(extract(&file, dir.path(), &ExtractOptions::default()).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
 use std::fs;
 use std::io::Write;
@@ -5,33 +6,42 @@ use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::time::Duration;
 
-use bytes::Bytes;
-use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use futures::stream::{self, StreamExt};
 use reqwest::header::{self, HeaderMap, HeaderValue};
 use reqwest::{multipart, IntoUrl, StatusCode, Url};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use tokio::io::{AsyncRead, AsyncWriteExt};
-use tokio_util::codec;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 
 use anyhow::{anyhow, Context};
 
-use crate::archive::ArchiveMode;
 use crate::error::{ApiError, KaggleError};
+use crate::filter::IgnoreFilter;
+use crate::ipynb::{Notebook, StripOptions};
+use crate::models::datapackage::DataPackage;
 use crate::models::extended::{
-    Competition, Dataset, DatasetMetadata, DatasetNewResponse, DatasetNewVersionResponse, File,
-    FileUploadInfo, Kernel, KernelOutput, KernelPullResponse, KernelPushResponse, LeaderBoard,
-    ListFilesResult, Submission, SubmitResult,
+    Competition, Dataset, DatasetCreated, DatasetFile, DatasetMetadata, DatasetNewResponse,
+    DatasetNewVersionResponse, File, FileUploadInfo, Kernel, KernelOutput, KernelOutputFile,
+    KernelPullResponse, KernelPushResponse, LeaderBoard, ListFilesResult, Submission, SubmitResult,
 };
-use crate::models::metadata::{Metadata, Resource};
+use crate::models::metadata::{DatasetMetadataFile, KernelMetadataFile, Resource};
 use crate::models::{
-    DatasetNew, DatasetNewRequest, DatasetNewVersionRequest, DatasetUpdateSettingsRequest,
-    DatasetUploadFile, Error, KernelPushRequest,
+    CompetitionRef, DatasetNew, DatasetNewRequest, DatasetNewVersionRequest, DatasetRef,
+    DatasetStatus, DatasetUpdateSettingsRequest, DatasetUploadFile, DatasetVersionPlan, Error,
+    IntoRef, KernelLog, KernelPushRequest, KernelRef, KernelStatus, MetadataDiff, MetadataKind,
+    PlannedFile,
 };
 use crate::query::{PushKernelType, PushLanguageType};
-use crate::request::{CompetitionsList, DatasetsList, KernelPullRequest, KernelsList};
+use crate::request::{
+    CompetitionsList, DatasetsList, DownloadProgress, KernelOutputRequest, KernelPullRequest,
+    KernelsList,
+};
+use crate::upload::resumable::{self, SessionStatus};
+use crate::upload::{
+    relative_name, ResumableOptions, UploadEntry, UploadOptions, UploadReport, UploadSession,
+    UploadSource,
+};
 use base64::engine::general_purpose::STANDARD;
-use std::collections::HashMap;
 
 use log::debug;
 use tempfile::TempDir;
@@ -49,6 +59,10 @@ pub struct KaggleApiClient {
     /// The client that executes the http requests
     client: Rc<reqwest::Client>,
 
+    /// The client for requests to signed storage urls, without the Kaggle
+    /// credentials
+    storage_client: Rc<reqwest::Client>,
+
     /// Base url to the kaggle api, `https://www.kaggle.com/api/v1`
     base_url: Url,
 
@@ -60,11 +74,11 @@ pub struct KaggleApiClient {
 }
 
 impl KaggleApiClient {
-    const DATASET_METADATA_FILE: &'static str = "dataset-metadata.json";
+    const DATASET_METADATA_FILE: &'static str = DatasetMetadataFile::FILE_NAME;
 
     const OLD_DATASET_METADATA_FILE: &'static str = "datapackage.json";
 
-    const KERNEL_METADATA_FILE: &'static str = "kernel-metadata.json";
+    const KERNEL_METADATA_FILE: &'static str = KernelMetadataFile::FILE_NAME;
 
     /// Convenience method to create a [`KaggleApiClientBuilder`]
     #[inline]
@@ -83,6 +97,7 @@ pub struct KaggleApiClientBuilder {
     base_url: Url,
     user_agent: Option<String>,
     client: Option<Rc<reqwest::Client>>,
+    storage_client: Option<Rc<reqwest::Client>>,
     headers: Option<HeaderMap>,
     auth: Option<Authentication>,
     download_dir: Option<PathBuf>,
@@ -111,6 +126,21 @@ impl KaggleApiClientBuilder {
         self
     }
 
+    /// Set the client for uploads to and downloads from signed storage urls.
+    ///
+    /// These requests must not carry the Kaggle credentials, so the client
+    /// should not have default authorization headers.
+    pub fn storage_client(mut self, storage_client: Rc<reqwest::Client>) -> Self {
+        self.storage_client = Some(storage_client);
+        self
+    }
+
+    /// Set the base url of the api, defaults to `https://www.kaggle.com/api/v1/`
+    pub fn base_url(mut self, base_url: Url) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
     pub fn client(mut self, client: Rc<reqwest::Client>) -> Self {
         self.client = Some(client);
         self
@@ -134,20 +164,25 @@ impl KaggleApiClientBuilder {
             write!(encoder, "{}", &credentials.key)?;
         }
 
-        headers.insert(header::AUTHORIZATION, header_value.try_into()?);
-        if let Some(user_agent) = self.user_agent {
-            headers.insert(header::USER_AGENT, user_agent.parse()?);
+        let user_agent = if let Some(user_agent) = self.user_agent {
+            user_agent.parse()?
         } else {
-            headers.insert(
-                header::USER_AGENT,
-                HeaderValue::from_static(concat!(
-                    env!("CARGO_PKG_NAME"),
-                    "/",
-                    env!("CARGO_PKG_VERSION"),
-                )),
-            );
-        }
+            HeaderValue::from_static(concat!(
+                env!("CARGO_PKG_NAME"),
+                "/",
+                env!("CARGO_PKG_VERSION"),
+            ))
+        };
+        headers.insert(header::USER_AGENT, user_agent.clone());
+
+        // signed storage urls reject requests with other credentials
+        let storage_client = if let Some(client) = self.storage_client {
+            client
+        } else {
+            Rc::new(reqwest::Client::builder().user_agent(user_agent).build()?)
+        };
 
+        headers.insert(header::AUTHORIZATION, header_value.try_into()?);
         let client = if let Some(client) = self.client {
             client
         } else {
@@ -166,6 +201,7 @@ impl KaggleApiClientBuilder {
 
         Ok(KaggleApiClient {
             client,
+            storage_client,
             base_url: self.base_url,
             credentials,
             download_dir,
@@ -179,6 +215,7 @@ impl Default for KaggleApiClientBuilder {
             base_url: "https://www.kaggle.com/api/v1/".parse().unwrap(),
             user_agent: None,
             client: None,
+            storage_client: None,
             headers: None,
             auth: None,
             download_dir: None,
@@ -298,6 +335,25 @@ impl KaggleApiClient {
         Ok(self.base_url.join(path.as_ref())?)
     }
 
+    /// Parse a dataset reference, datasets without an owner belong to the
+    /// authenticated user.
+    fn dataset_ref(&self, name: impl IntoRef<DatasetRef>) -> Result<DatasetRef, KaggleError> {
+        name.into_ref(&self.credentials.username)
+    }
+
+    /// Parse a kernel reference, kernels without an owner belong to the
+    /// authenticated user.
+    fn kernel_ref(&self, name: impl IntoRef<KernelRef>) -> Result<KernelRef, KaggleError> {
+        name.into_ref(&self.credentials.username)
+    }
+
+    fn competition_ref(
+        &self,
+        id: impl IntoRef<CompetitionRef>,
+    ) -> Result<CompetitionRef, KaggleError> {
+        id.into_ref(&self.credentials.username)
+    }
+
     /// Determine if a dataset string is valid, meaning it is in the format of
     /// {username}/{identifier-slug}
     pub fn get_user_and_identifier_slug<'a>(
@@ -407,13 +463,20 @@ impl KaggleApiClient {
 
     pub(crate) async fn read_dataset_metadata_file(
         path: impl AsRef<Path>,
-    ) -> anyhow::Result<Metadata> {
+    ) -> anyhow::Result<DatasetMetadataFile> {
         let meta_file = Self::get_dataset_metadata_file(path)?;
         let file = tokio::fs::read(&meta_file).await?;
-        Ok(serde_json::from_slice(&file)?)
+        if meta_file.file_name() == Some(Self::OLD_DATASET_METADATA_FILE.as_ref()) {
+            let package: DataPackage = serde_json::from_slice(&file)?;
+            Ok(package.into())
+        } else {
+            Ok(serde_json::from_slice(&file)?)
+        }
     }
 
-    async fn read_kernel_metadata_file(path: impl AsRef<Path>) -> anyhow::Result<Metadata> {
+    async fn read_kernel_metadata_file(
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<KernelMetadataFile> {
         let meta_file = Self::get_kernel_metadata_file(path)?;
         let file = tokio::fs::read(&meta_file).await?;
         Ok(serde_json::from_slice(&file)?)
@@ -456,36 +519,103 @@ impl KaggleApiClient {
         }
     }
 
-    fn get_file_metadata(file: impl AsRef<Path>) -> anyhow::Result<(u64, Duration)> {
-        let file = file.as_ref();
-        let meta = file.metadata()?;
-        let content_length = meta.len();
-        let last_modified = meta
-            .modified()
-            .unwrap_or_else(|_| std::time::SystemTime::now())
-            .elapsed()?;
-
-        Ok((content_length, last_modified))
-    }
-
     /// Upload a single dataset file.
+    ///
+    /// Large files are uploaded in chunks to a resumable upload session if
+    /// `resumable` is set. The session is persisted in `state_file`, or in
+    /// the [`UploadSession::state_file`] of the file if unset.
     async fn upload_dataset_file(
         &self,
         file: impl AsRef<Path>,
         file_name: impl AsRef<str>,
         item: Option<&Resource>,
+        resumable: Option<&ResumableOptions>,
+        state_file: Option<PathBuf>,
     ) -> anyhow::Result<DatasetUploadFile> {
-        let file = file.as_ref();
-        let (content_length, last_modified) = Self::get_file_metadata(file)?;
-        // get the token first
-        let info = self
-            .datasets_upload_file(file_name.as_ref(), content_length, last_modified)
-            .await?;
+        let source = UploadSource::file(file)?.name(file_name.as_ref());
+        self.upload_dataset_source(source, item, resumable, state_file)
+            .await
+    }
+
+    /// Upload a single dataset file from a buffer or reader.
+    ///
+    /// The returned upload can be added to the files of a
+    /// [`DatasetNewRequest`] or [`DatasetNewVersionRequest`].
+    ///
+    /// Example
+    ///
+    /// ```no_run
+    /// use kaggle::models::DatasetNewVersionRequest;
+    /// use kaggle::upload::UploadSource;
+    /// use kaggle::KaggleApiClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let kaggle = KaggleApiClient::builder().build()?;
+    ///     let file = kaggle
+    ///         .dataset_upload_source(UploadSource::bytes("data.csv", "a,b\n1,2\n"))
+    ///         .await?;
+    ///     let mut req = DatasetNewVersionRequest::new("Generated data".to_string());
+    ///     req.set_files(vec![file]);
+    ///     kaggle.datasets_create_version("me/my-dataset", &req).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn dataset_upload_source(
+        &self,
+        source: UploadSource,
+    ) -> anyhow::Result<DatasetUploadFile> {
+        self.upload_dataset_source(source, None, None, None).await
+    }
+
+    async fn upload_dataset_source(
+        &self,
+        source: UploadSource,
+        item: Option<&Resource>,
+        resumable: Option<&ResumableOptions>,
+        state_file: Option<PathBuf>,
+    ) -> anyhow::Result<DatasetUploadFile> {
+        let content_length = source.content_length();
+        let last_modified = source.last_modified_since_epoch();
+
+        let chunked = resumable
+            .filter(|r| r.is_chunked(content_length))
+            .and_then(|r| source.path().map(|path| (r, path.to_path_buf())));
+        let token = match chunked {
+            Some((resumable, path)) => {
+                let state_file = match state_file {
+                    Some(state_file) => state_file,
+                    None => UploadSession::state_file(&resumable.state_dir, &path)?,
+                };
+                self.upload_resumable(
+                    &path,
+                    source.file_name(),
+                    content_length,
+                    last_modified,
+                    resumable,
+                    &state_file,
+                )
+                .await?
+            }
+            None => {
+                // get the token first
+                let info = self
+                    .datasets_upload_file(source.file_name(), content_length, last_modified)
+                    .await?;
+
+                // complete the upload to retrieve a path from the url parameter
+                self.upload_complete(source, &info.create_url).await?;
+                info.token
+            }
+        };
 
-        // complete the upload to retrieve a path from the url parameter
-        self.upload_complete(file, &info.create_url).await?;
+        Ok(Self::described_upload_file(token, item))
+    }
 
-        let mut upload_file = DatasetUploadFile::new(info.token);
+    /// The uploaded file with the token of the upload and the description
+    /// and columns of its `item` in the metadata.
+    fn described_upload_file(token: impl ToString, item: Option<&Resource>) -> DatasetUploadFile {
+        let mut upload_file = DatasetUploadFile::new(token);
         if let Some(item) = item {
             if let Some(desc) = &item.description {
                 upload_file.set_description(desc.clone());
@@ -493,71 +623,456 @@ impl KaggleApiClient {
             if let Some(schema) = &item.schema {
                 upload_file.set_columns(schema.get_processed_columns());
             }
-            if let Some(schema) = &item.schema {
-                upload_file.set_columns(schema.get_processed_columns());
+        }
+        upload_file
+    }
+
+    /// Upload the file in chunks to a resumable upload session and return the
+    /// upload's token.
+    ///
+    /// If a session for the file was persisted in `state_file`, the upload
+    /// continues with the first byte that was not committed yet. The
+    /// persisted session is removed once the upload is complete.
+    async fn upload_resumable(
+        &self,
+        file: &Path,
+        file_name: &str,
+        content_length: u64,
+        last_modified: Duration,
+        options: &ResumableOptions,
+        state_file: &Path,
+    ) -> anyhow::Result<String> {
+        let mut committed = 0;
+        let mut session = None;
+        if let Some(mut existing) = UploadSession::load(state_file)? {
+            // an archive with the same content may have been created at
+            // another temporary path
+            existing.path = file.to_path_buf();
+            match self.upload_session_status(&existing).await {
+                Ok(SessionStatus::Complete) => {
+                    fs::remove_file(state_file)?;
+                    return Ok(existing.token);
+                }
+                Ok(SessionStatus::Incomplete(bytes)) => {
+                    debug!("Resuming upload of {} at byte {}", file.display(), bytes);
+                    committed = bytes;
+                    session = Some(existing);
+                }
+                Err(err) if is_session_expired(&err) => {
+                    debug!("Upload session for {} expired", file.display());
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        let session = match session {
+            Some(session) => session,
+            None => {
+                let info = self
+                    .datasets_upload_file(file_name, content_length, last_modified)
+                    .await?;
+                let session = UploadSession {
+                    path: file.to_path_buf(),
+                    total_bytes: content_length,
+                    session_url: self.start_upload_session(&info.create_url).await?,
+                    token: info.token,
+                };
+                session.save(state_file)?;
+                session
+            }
+        };
+
+        self.upload_chunks(&session, committed, options.aligned_chunk_size())
+            .await?;
+        fs::remove_file(state_file)?;
+        Ok(session.token)
+    }
+
+    /// Start a resumable upload session for the signed `create_url` and return
+    /// the session uri.
+    async fn start_upload_session(&self, create_url: &str) -> anyhow::Result<String> {
+        let resp = Self::request(
+            self.storage_client
+                .post(create_url)
+                .header("x-goog-resumable", "start")
+                .header(header::CONTENT_LENGTH, 0),
+        )
+        .await?;
+        let location = resp
+            .headers()
+            .get(header::LOCATION)
+            .context("The upload session was started without a session uri")?
+            .to_str()?;
+        Ok(Url::parse(create_url)?.join(location)?.to_string())
+    }
+
+    /// Query how many bytes of the upload session were committed.
+    async fn upload_session_status(
+        &self,
+        session: &UploadSession,
+    ) -> anyhow::Result<SessionStatus> {
+        let resp = self
+            .storage_client
+            .put(&session.session_url)
+            .header(header::CONTENT_LENGTH, 0)
+            .header(
+                header::CONTENT_RANGE,
+                format!("bytes */{}", session.total_bytes),
+            )
+            .send()
+            .await?;
+        Self::session_status(resp)
+    }
+
+    /// Send the file's content, starting at `offset`, in chunks of
+    /// `chunk_size` bytes.
+    async fn upload_chunks(
+        &self,
+        session: &UploadSession,
+        mut offset: u64,
+        chunk_size: usize,
+    ) -> anyhow::Result<()> {
+        let total = session.total_bytes;
+        let mut file = tokio::fs::File::open(&session.path).await?;
+        let mut buffer = vec![0; chunk_size];
+        while offset < total {
+            let len = (total - offset).min(chunk_size as u64) as usize;
+            file.seek(std::io::SeekFrom::Start(offset)).await?;
+            file.read_exact(&mut buffer[..len]).await?;
+
+            let resp = self
+                .storage_client
+                .put(&session.session_url)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", offset, offset + len as u64 - 1, total),
+                )
+                .body(buffer[..len].to_vec())
+                .send()
+                .await?;
+            match Self::session_status(resp)? {
+                SessionStatus::Complete => return Ok(()),
+                SessionStatus::Incomplete(committed) => offset = committed,
             }
         }
+        match self.upload_session_status(session).await? {
+            SessionStatus::Complete => Ok(()),
+            SessionStatus::Incomplete(committed) => Err(anyhow!(
+                "Upload session of {} is incomplete after sending all bytes, {} of {} committed",
+                session.path.display(),
+                committed,
+                total
+            )),
+        }
+    }
 
-        Ok(upload_file)
+    /// `200` and `201` complete an upload session, `308` reports the bytes
+    /// committed so far.
+    fn session_status(resp: reqwest::Response) -> anyhow::Result<SessionStatus> {
+        match resp.status() {
+            StatusCode::OK | StatusCode::CREATED => Ok(SessionStatus::Complete),
+            StatusCode::PERMANENT_REDIRECT => Ok(SessionStatus::Incomplete(
+                resumable::committed_bytes(resp.headers())?,
+            )),
+            StatusCode::UNAUTHORIZED => Err(KaggleError::Api {
+                err: ApiError::Unauthorized,
+            }
+            .into()),
+            status => Err(KaggleError::Api {
+                err: ApiError::Other(status.as_u16()),
+            }
+            .into()),
+        }
     }
 
     /// Upload files in a folder.
+    ///
+    /// Which files are uploaded is determined by the mode of the `options`,
+    /// the `resources` add descriptions and schemas to the uploads. Up to
+    /// `options.concurrency` files are uploaded at the same time, each is
+    /// retried `options.retries` times.
+    ///
+    /// If any file fails to upload, this returns a
+    /// [`KaggleError::PartialUpload`] with the tokens of all successful
+    /// uploads.
     async fn upload_files(
         &self,
         folder: impl AsRef<Path>,
         resources: &[Resource],
-        dir_mode: ArchiveMode,
+        options: &UploadOptions,
     ) -> anyhow::Result<Vec<DatasetUploadFile>> {
-        let mut uploads = Vec::with_capacity(resources.len());
         let folder = folder.as_ref();
+        let filter = options.ignore_filter(folder)?;
+        let entries = options.entries(folder, resources, &filter)?;
+
+        let tmp_archive_dir = TempDir::new()?;
+        let tmp_archive_dir = tmp_archive_dir.path();
+
+        let mut results: Vec<_> = stream::iter(entries.into_iter().enumerate())
+            .map(|(idx, entry)| {
+                let filter = &filter;
+                async move {
+                    let result = if let Some(done) = options.completed.get(&entry.path) {
+                        debug!("Skipping already uploaded {}", entry.path.display());
+                        Ok(Some(done.clone()))
+                    } else {
+                        // a directory per entry keeps archives of directories
+                        // with the same name apart
+                        let archive_dir = tmp_archive_dir.join(idx.to_string());
+                        self.upload_entry(&entry, options, filter, &archive_dir)
+                            .await
+                    };
+                    (idx, entry.path, result)
+                }
+            })
+            .buffer_unordered(options.concurrency.max(1))
+            .collect()
+            .await;
+        results.sort_by_key(|(idx, _, _)| *idx);
+
+        let mut report = UploadReport::default();
+        for (_, path, result) in results {
+            match result {
+                Ok(Some(upload)) => report.uploaded.push((path, upload)),
+                Ok(None) => {}
+                Err(err) => report.failed.push((path, err)),
+            }
+        }
+        if report.failed.is_empty() {
+            Ok(report.uploaded.into_iter().map(|(_, f)| f).collect())
+        } else {
+            Err(KaggleError::PartialUpload { report }.into())
+        }
+    }
 
-        let resource_paths: HashMap<_, _> = resources
-            .iter()
-            .map(|x| (folder.join(&x.path), x))
-            .collect();
-
-        let mut tmp_archive_dir = None;
-
-        let skip = &[
-            Self::DATASET_METADATA_FILE,
-            Self::OLD_DATASET_METADATA_FILE,
-            Self::KERNEL_METADATA_FILE,
-        ];
+    /// Upload a single file or archived directory of a dataset folder,
+    /// retrying failed uploads.
+    ///
+    /// Returns `None` if the entry is a directory that is not archived.
+    async fn upload_entry(
+        &self,
+        entry: &UploadEntry<'_>,
+        options: &UploadOptions,
+        filter: &IgnoreFilter,
+        archive_dir: &Path,
+    ) -> anyhow::Result<Option<DatasetUploadFile>> {
+        let upload = if entry.path.is_dir() {
+            tokio::fs::create_dir_all(archive_dir).await?;
+            let archive_path = archive_dir.join(
+                entry
+                    .path
+                    .file_name()
+                    .context("File path terminates in `..`")?,
+            );
+            let mode = options.archive_mode.clone();
+            let archive_options = options.archive_options.clone();
+            let filter = filter.clone();
+            let src = entry.path.clone();
+            match tokio::task::spawn_blocking(move || {
+                mode.make_archive_filtered(src, archive_path, &archive_options, &filter)
+            })
+            .await??
+            {
+                Some(archive) => archive,
+                None => return Ok(None),
+            }
+        } else {
+            entry.path.clone()
+        };
 
-        for (entry, resource) in resource_paths {
-            if !entry.exists() {
-                continue;
+        let mut state_file = None;
+        if upload != entry.path
+            && (!options.known_archives.is_empty() || options.resumable.is_some())
+        {
+            let path = upload.clone();
+            let digest =
+                tokio::task::spawn_blocking(move || crate::archive::sha256_digest(path)).await??;
+            if let Some(known) = options.known_archives.get(&digest) {
+                debug!("Skipping unchanged archive of {}", entry.path.display());
+                return Ok(Some(Self::described_upload_file(
+                    known.token(),
+                    entry.resource,
+                )));
+            }
+            if let Some(resumable) = &options.resumable {
+                state_file = Some(UploadSession::archive_state_file(
+                    &resumable.state_dir,
+                    &entry.path,
+                    &digest,
+                )?);
             }
-            let file_name = entry
+        }
+
+        let file_name = if upload == entry.path {
+            entry.name.as_str()
+        } else {
+            upload
                 .file_name()
                 .context("File path terminates in `..`")?
                 .to_str()
                 .context("File name is not valid unicode")?
-                .to_string();
-
-            let mut upload = None;
+        };
 
-            if entry.is_file() {
-                if skip.contains(&file_name.as_str()) {
-                    continue;
-                }
-                upload = Some(entry);
-            } else if entry.is_dir() {
-                if tmp_archive_dir.is_none() {
-                    tmp_archive_dir = Some(TempDir::new()?.path().join("kaggle-upload"));
+        let mut retries = 0;
+        let mut delay = options.retry_delay;
+        loop {
+            match self
+                .upload_dataset_file(
+                    &upload,
+                    file_name,
+                    entry.resource,
+                    options.resumable.as_ref(),
+                    state_file.clone(),
+                )
+                .await
+            {
+                Ok(upload_file) => return Ok(Some(upload_file)),
+                Err(err) if retries < options.retries && is_retryable(&err) => {
+                    retries += 1;
+                    debug!(
+                        "Upload of {} failed, retry {}/{}: {}",
+                        upload.display(),
+                        retries,
+                        options.retries,
+                        err
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay = delay
+                        .checked_mul(2)
+                        .map_or(MAX_RETRY_DELAY, |d| d.min(MAX_RETRY_DELAY))
+                        .max(options.retry_delay);
                 }
-                let archive_path = tmp_archive_dir.as_ref().unwrap().as_path().join(&file_name);
-                upload = dir_mode.make_archive(entry, &archive_path)?;
+                Err(err) => return Err(err),
             }
+        }
+    }
+}
 
-            if let Some(upload) = upload {
-                let upload_file = self
-                    .upload_dataset_file(upload, &file_name, Some(resource))
-                    .await?;
-                uploads.push(upload_file);
+/// Upload sessions that expired or were cancelled are reported as `404` or
+/// `410`.
+fn is_session_expired(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<KaggleError>(),
+        Some(KaggleError::Api {
+            err: ApiError::Other(404 | 410)
+        })
+    )
+}
+
+/// The longest delay between two retries of an upload, unless the initial
+/// delay is even longer.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Whether a failed request is worth retrying, which are transport errors,
+/// `429 Too Many Requests` and server errors.
+fn is_retryable(err: &anyhow::Error) -> bool {
+    if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+        return match err.status() {
+            Some(status) => is_retryable_status(status.as_u16().into()),
+            None => err.is_timeout() || err.is_connect() || err.is_request() || err.is_body(),
+        };
+    }
+    match err.downcast_ref::<KaggleError>() {
+        Some(KaggleError::Api {
+            err: ApiError::Other(status),
+        }) => is_retryable_status((*status).into()),
+        Some(KaggleError::Api {
+            err: ApiError::ServerError(err),
+        }) => is_retryable_status(err.code()),
+        _ => false,
+    }
+}
+
+fn is_retryable_status(status: i32) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+/// Poll `status` every `poll_interval` until `check` returns a result or an
+/// error.
+///
+/// Returns a [`KaggleError::Timeout`] for `name` if `check` still returned
+/// `None` after `timeout`.
+async fn poll_until<S, T, F, Fut>(
+    name: &str,
+    timeout: Duration,
+    poll_interval: Duration,
+    mut status: F,
+    mut check: impl FnMut(S) -> anyhow::Result<Option<T>>,
+) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<S>>,
+{
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        if let Some(done) = check(status().await?)? {
+            return Ok(done);
+        }
+        let now = tokio::time::Instant::now();
+        if now >= deadline {
+            return Err(KaggleError::Timeout {
+                name: name.to_string(),
+                timeout,
             }
+            .into());
+        }
+        tokio::time::sleep(poll_interval.min(deadline - now)).await;
+    }
+}
+
+/// Tracks whether the polled status belongs to a version that was just
+/// created.
+///
+/// Status endpoints report the latest version, which can still be the
+/// previous one right after a new version was created. A status only counts
+/// once it reports the `expected` version or, if the server doesn't report
+/// versions, once it was seen unfinished.
+struct NewVersionStatus {
+    expected: Option<u64>,
+    started: bool,
+}
+
+impl NewVersionStatus {
+    fn new(expected: Option<u64>) -> Self {
+        Self {
+            expected,
+            started: false,
+        }
+    }
+
+    /// Whether the status `value`, which is `finished` or not, belongs to
+    /// the new version.
+    fn is_current(&mut self, value: &serde_json::Value, finished: bool) -> bool {
+        let reported = value.get("versionNumber").and_then(|v| v.as_u64());
+        match (self.expected, reported) {
+            (Some(expected), Some(reported)) => self.started |= reported >= expected,
+            _ => self.started |= !finished,
+        }
+        self.started
+    }
+}
+
+/// The outcome of a kernel run, `None` while it is still queued or running.
+fn kernel_run_result(
+    kernel: &KernelRef,
+    status: KernelStatus,
+) -> anyhow::Result<Option<KernelStatus>> {
+    match status {
+        KernelStatus::Complete => Ok(Some(KernelStatus::Complete)),
+        KernelStatus::Error(msg) => Err(KaggleError::KernelFailed {
+            kernel: kernel.to_string(),
+            msg,
+        }
+        .into()),
+        KernelStatus::Cancelled => Err(KaggleError::KernelFailed {
+            kernel: kernel.to_string(),
+            msg: "the run was cancelled".to_string(),
+        }
+        .into()),
+        status => {
+            debug!("Kernel {} is {:?}", kernel, status);
+            Ok(None)
         }
-        Ok(uploads)
     }
 }
 
@@ -609,10 +1124,10 @@ impl KaggleApiClient {
     /// ```
     pub async fn competition_download_leaderboard(
         &self,
-        id: impl AsRef<str>,
+        id: impl IntoRef<CompetitionRef>,
         output: Option<PathBuf>,
     ) -> anyhow::Result<PathBuf> {
-        let id = id.as_ref();
+        let id = self.competition_ref(id)?;
         let output = if let Some(target) = output {
             if target.is_dir() {
                 target.join(format!("{}-leaderboard.zip", id))
@@ -648,11 +1163,12 @@ impl KaggleApiClient {
     /// ```
     pub async fn competition_view_leaderboard(
         &self,
-        id: impl AsRef<str>,
+        id: impl IntoRef<CompetitionRef>,
     ) -> anyhow::Result<LeaderBoard> {
+        let id = self.competition_ref(id)?;
         Self::request_json(
             self.client
-                .get(self.join_url(format!("competitions/{}/leaderboard/view", id.as_ref()))?),
+                .get(self.join_url(format!("competitions/{}/leaderboard/view", id))?),
         )
         .await
     }
@@ -690,19 +1206,17 @@ impl KaggleApiClient {
     /// ```
     pub async fn competitions_data_download_file(
         &self,
-        id: impl AsRef<str>,
+        id: impl IntoRef<CompetitionRef>,
         file_name: impl AsRef<str>,
         target: Option<PathBuf>,
     ) -> anyhow::Result<PathBuf> {
+        let id = self.competition_ref(id)?;
         let file_name = file_name.as_ref();
         let output = target.unwrap_or_else(|| self.download_dir.join(format!("{}.zip", file_name)));
 
         Self::download_file(
-            self.client.get(self.join_url(format!(
-                "competitions/data/download/{}/{}",
-                id.as_ref(),
-                file_name
-            ))?),
+            self.client
+                .get(self.join_url(format!("competitions/data/download/{}/{}", id, file_name))?),
             output,
         )
         .await
@@ -739,10 +1253,10 @@ impl KaggleApiClient {
     /// ```
     pub async fn competitions_data_download_all_files(
         &self,
-        id: impl AsRef<str>,
+        id: impl IntoRef<CompetitionRef>,
         target: Option<PathBuf>,
     ) -> anyhow::Result<PathBuf> {
-        let id = id.as_ref();
+        let id = self.competition_ref(id)?;
         let output = target.unwrap_or_else(|| self.download_dir.join(format!("{}.zip", id)));
 
         Self::download_file(
@@ -775,11 +1289,12 @@ impl KaggleApiClient {
     /// ```
     pub async fn competitions_data_list_files(
         &self,
-        id: impl AsRef<str>,
+        id: impl IntoRef<CompetitionRef>,
     ) -> anyhow::Result<Vec<File>> {
+        let id = self.competition_ref(id)?;
         Self::request_json(
             self.client
-                .get(self.join_url(format!("competitions/data/list/{}", id.as_ref()))?),
+                .get(self.join_url(format!("competitions/data/list/{}", id))?),
         )
         .await
     }
@@ -787,12 +1302,13 @@ impl KaggleApiClient {
     /// Get the list submissions for a particular competition
     pub async fn competitions_submissions_list(
         &self,
-        id: impl AsRef<str>,
+        id: impl IntoRef<CompetitionRef>,
         page: usize,
     ) -> anyhow::Result<Vec<Submission>> {
+        let id = self.competition_ref(id)?;
         let req = self
             .client
-            .get(self.join_url(format!("competitions/submissions/list/{}", id.as_ref()))?)
+            .get(self.join_url(format!("competitions/submissions/list/{}", id))?)
             .query(&[("page", page)]);
 
         Self::request_json(req).await
@@ -801,17 +1317,18 @@ impl KaggleApiClient {
     /// Submit to competition.
     pub async fn competitions_submissions_submit(
         &self,
-        id: impl AsRef<str>,
+        id: impl IntoRef<CompetitionRef>,
         blob_file_tokens: impl ToString,
         submission_description: impl ToString,
     ) -> anyhow::Result<SubmitResult> {
+        let id = self.competition_ref(id)?;
         let form = multipart::Form::new()
             .text("blobFileTokens", blob_file_tokens.to_string())
             .text("submissionDescription", submission_description.to_string());
 
         Self::request_json(
             self.client
-                .post(self.join_url(format!("competitions/submissions/submit/{}", id.as_ref()))?)
+                .post(self.join_url(format!("competitions/submissions/submit/{}", id))?)
                 .multipart(form),
         )
         .await
@@ -821,21 +1338,31 @@ impl KaggleApiClient {
     pub async fn competition_submit(
         &self,
         file: impl AsRef<Path>,
-        competition: impl AsRef<str>,
+        competition: impl IntoRef<CompetitionRef>,
         message: impl ToString,
     ) -> anyhow::Result<SubmitResult> {
-        let competition = competition.as_ref();
-        let file = file.as_ref();
-        let (content_length, last_modified) = Self::get_file_metadata(file)?;
+        self.competition_submit_source(UploadSource::file(file)?, competition, message)
+            .await
+    }
 
-        let file_name = file
-            .file_name()
-            .context("File path terminates in `..`")?
-            .to_str()
-            .context("File name is not valid unicode")?;
+    /// Submit a competition from a buffer or reader, see [`UploadSource`].
+    pub async fn competition_submit_source(
+        &self,
+        source: UploadSource,
+        competition: impl IntoRef<CompetitionRef>,
+        message: impl ToString,
+    ) -> anyhow::Result<SubmitResult> {
+        let competition = self.competition_ref(competition)?;
+        let content_length = source.content_length();
+        let last_modified = source.last_modified_since_epoch();
 
         let url_result = self
-            .competitions_submissions_url(&competition, content_length, last_modified, file_name)
+            .competitions_submissions_url(
+                &competition,
+                content_length,
+                last_modified,
+                source.file_name(),
+            )
             .await?;
 
         let obj = url_result
@@ -857,7 +1384,7 @@ impl KaggleApiClient {
                 ));
             }
             self.competitions_submissions_upload(
-                file,
+                source,
                 parts[0],
                 parts[1].parse()?,
                 Duration::from_secs(parts[2].parse()?),
@@ -865,7 +1392,7 @@ impl KaggleApiClient {
             .await?
         } else {
             self.upload_complete(
-                file,
+                source,
                 obj.get("createUrl")
                     .and_then(serde_json::Value::as_str)
                     .context("Missing createUrl in response")?,
@@ -886,15 +1413,15 @@ impl KaggleApiClient {
 
     async fn upload_complete(
         &self,
-        file: impl AsRef<Path>,
+        source: UploadSource,
         url: impl IntoUrl,
     ) -> anyhow::Result<reqwest::Response> {
-        let stream = into_bytes_stream(tokio::fs::File::open(file).await?);
-
+        let content_length = source.content_length();
         Self::request(
-            self.client
+            self.storage_client
                 .put(url)
-                .body(reqwest::Body::wrap_stream(stream)),
+                .header(header::CONTENT_LENGTH, content_length)
+                .body(source.into_body().await?),
         )
         .await
     }
@@ -902,17 +1429,16 @@ impl KaggleApiClient {
     /// Upload competition submission file
     async fn competitions_submissions_upload(
         &self,
-        file: impl AsRef<Path>,
+        source: UploadSource,
         guid: impl AsRef<str>,
         content_length: u64,
         last_modified_date_utc: Duration,
     ) -> anyhow::Result<serde_json::Value> {
-        let stream = into_bytes_stream(tokio::fs::File::open(file).await?);
-
-        let form = multipart::Form::new().part(
-            "file",
-            multipart::Part::stream(reqwest::Body::wrap_stream(stream)),
-        );
+        let file_name = source.file_name().to_string();
+        let source_length = source.content_length();
+        let part = multipart::Part::stream_with_length(source.into_body().await?, source_length)
+            .file_name(file_name);
+        let form = multipart::Form::new().part("file", part);
 
         let req = self
             .client
@@ -930,7 +1456,7 @@ impl KaggleApiClient {
     /// Generate competition submission URL
     async fn competitions_submissions_url(
         &self,
-        id: impl AsRef<str>,
+        id: &CompetitionRef,
         content_length: u64,
         last_modified_date_utc: Duration,
         file_name: impl ToString,
@@ -941,7 +1467,7 @@ impl KaggleApiClient {
             .client
             .post(self.join_url(format!(
                 "competitions/{}/submissions/url/{}/{}",
-                id.as_ref(),
+                id,
                 content_length,
                 last_modified_date_utc.as_secs()
             ))?)
@@ -949,12 +1475,33 @@ impl KaggleApiClient {
         Self::request_json(req).await
     }
 
+    /// Write a starter `dataset-metadata.json` into `folder` that is owned by
+    /// the authenticated user, see [`DatasetMetadataFile::init`].
+    pub fn dataset_metadata_init(
+        &self,
+        folder: impl AsRef<Path>,
+        force: bool,
+    ) -> anyhow::Result<PathBuf> {
+        DatasetMetadataFile::init(folder, &self.credentials.username, force)
+    }
+
+    /// Write a starter `kernel-metadata.json` into `folder` that is owned by
+    /// the authenticated user, see [`KernelMetadataFile::init`].
+    pub fn kernel_metadata_init(
+        &self,
+        folder: impl AsRef<Path>,
+        force: bool,
+    ) -> anyhow::Result<PathBuf> {
+        KernelMetadataFile::init(folder, &self.credentials.username, force)
+    }
+
     /// Create a new dataset meaning the same as creating a version but with
     /// extra metadata like license and user/owner.
     ///
-    /// This will upload any referenced resources in the metadata resources.
-    /// This will fail on kaggle if the metadata contains no resources to
-    /// upload.
+    /// By default this uploads every file and directory of the dataset folder
+    /// that is not ignored, the metadata resources attach descriptions and
+    /// schemas, see [`UploadMode`](crate::upload::UploadMode).
+    /// This will fail on kaggle if there are no files to upload.
     ///
     /// Example
     ///
@@ -994,54 +1541,23 @@ impl KaggleApiClient {
     pub async fn dataset_create_new(
         &self,
         new_dataset: DatasetNew,
-    ) -> anyhow::Result<DatasetNewResponse> {
-        new_dataset.validate_resources()?;
+    ) -> anyhow::Result<DatasetCreated> {
+        new_dataset
+            .metadata
+            .validate_in(MetadataKind::Dataset, new_dataset.dataset_folder.as_deref())
+            .into_result()?;
         let metadata = new_dataset.metadata;
 
-        let (owner_slug, dataset_slug) = self
-            .get_user_and_identifier_slug(&metadata.id)
-            .map(|(s1, s2)| (s1.to_string(), s2.to_string()))?;
-
-        // validate
-        if dataset_slug == "INSERT_SLUG_HERE" {
-            return Err(KaggleError::meta(
-                "Default slug detected, please change values before uploading",
-            )
-            .into());
-        }
-        if metadata.title == "INSERT_SLUG_HERE" {
-            return Err(KaggleError::meta(
-                "Default title detected, please change values before uploading",
-            )
-            .into());
-        }
-        if metadata.licenses.len() != 1 {
-            return Err(KaggleError::meta("Please specify exactly one license").into());
-        }
-        if dataset_slug.len() < 6 || dataset_slug.len() > 50 {
-            return Err(
-                KaggleError::meta("The dataset slug must be between 6 and 50 characters").into(),
-            );
-        }
-        if metadata.title.len() < 6 || metadata.title.len() > 50 {
-            return Err(
-                KaggleError::meta("The dataset title must be between 6 and 50 characters").into(),
-            );
-        }
+        let dataset = self.dataset_ref(&metadata.id)?;
+        let (owner_slug, dataset_slug) = (dataset.owner(), dataset.slug());
 
         let mut request = DatasetNewRequest::builder(metadata.title);
         if let Some(subtitle) = &metadata.subtitle {
-            if subtitle.len() < 20 || subtitle.len() > 80 {
-                return Err(KaggleError::meta(
-                    "Subtitle length must be between 20 and 80 characters",
-                )
-                .into());
-            }
             request = request.subtitle(subtitle);
         }
 
         let files = if let Some(folder) = new_dataset.dataset_folder {
-            self.upload_files(folder, &metadata.resources, new_dataset.archive_mode)
+            self.upload_files(folder, &metadata.resources, &new_dataset.upload_options)
                 .await?
         } else {
             vec![]
@@ -1066,9 +1582,11 @@ impl KaggleApiClient {
     async fn datasets_create_new(
         &self,
         new_dataset: &DatasetNewRequest,
-    ) -> anyhow::Result<DatasetNewResponse> {
-        self.post_json(self.join_url("datasets/create/new")?, Some(new_dataset))
-            .await
+    ) -> anyhow::Result<DatasetCreated> {
+        let resp: DatasetNewResponse = self
+            .post_json(self.join_url("datasets/create/new")?, Some(new_dataset))
+            .await?;
+        Ok(resp.into_result()?)
     }
 
     /// Create a new dataset version
@@ -1078,26 +1596,22 @@ impl KaggleApiClient {
         version_notes: impl ToString,
         convert_to_csv: bool,
         delete_old_versions: bool,
-        archive_mode: ArchiveMode,
-    ) -> anyhow::Result<DatasetNewVersionResponse> {
+        upload_options: impl Into<UploadOptions>,
+    ) -> anyhow::Result<DatasetCreated> {
         let folder = folder.as_ref();
         let meta_data = Self::read_dataset_metadata_file(folder).await?;
-        meta_data.validate_resource(folder)?;
+        meta_data
+            .validate(MetadataKind::DatasetVersion, folder)
+            .into_result()?;
 
         let mut req = DatasetNewVersionRequest::new(version_notes.to_string());
 
         if let Some(subtitle) = meta_data.subtitle {
-            if subtitle.len() < 20 || subtitle.len() > 80 {
-                return Err(KaggleError::Metadata {
-                    msg: "Subtitle length must be between 20 and 80 characters".to_string(),
-                }
-                .into());
-            }
             req.set_subtitle(subtitle);
         }
 
         let files = self
-            .upload_files(folder, &meta_data.resources, archive_mode)
+            .upload_files(folder, &meta_data.resources, &upload_options.into())
             .await?;
 
         if let Some(desc) = meta_data.description {
@@ -1109,34 +1623,31 @@ impl KaggleApiClient {
         req.set_files(files);
 
         if let Some(id_no) = meta_data.id_no {
-            Ok(self.datasets_create_version_by_id(id_no, &req).await?)
+            self.datasets_create_version_by_id(id_no, &req).await
         } else {
-            if meta_data.id == format!("{}/INSERT_SLUG_HERE", self.credentials.username) {
-                return Err(KaggleError::Metadata {
-                    msg: "Default slug detected, please change values before uploading".to_string(),
-                }
-                .into());
-            }
-            Ok(self.datasets_create_version(&meta_data.id, &req).await?)
+            self.datasets_create_version(&meta_data.id, &req).await
         }
     }
 
     /// Create a new dataset version
     pub async fn datasets_create_version(
         &self,
-        name: &str,
+        name: impl IntoRef<DatasetRef>,
         dataset_req: &DatasetNewVersionRequest,
-    ) -> anyhow::Result<DatasetNewVersionResponse> {
-        let (owner_slug, dataset_slug) = self.get_user_and_identifier_slug(name)?;
-
-        self.post_json(
-            self.join_url(format!(
-                "datasets/create/version/{}/{}",
-                owner_slug, dataset_slug
-            ))?,
-            Some(dataset_req),
-        )
-        .await
+    ) -> anyhow::Result<DatasetCreated> {
+        let dataset = self.dataset_ref(name)?;
+        let (owner_slug, dataset_slug) = (dataset.owner(), dataset.slug());
+
+        let resp: DatasetNewVersionResponse = self
+            .post_json(
+                self.join_url(format!(
+                    "datasets/create/version/{}/{}",
+                    owner_slug, dataset_slug
+                ))?,
+                Some(dataset_req),
+            )
+            .await?;
+        Ok(resp.into_result()?)
     }
 
     /// Create a new dataset version by id
@@ -1144,21 +1655,23 @@ impl KaggleApiClient {
         &self,
         id: i32,
         dataset_req: &DatasetNewVersionRequest,
-    ) -> anyhow::Result<DatasetNewVersionResponse> {
-        self.post_json(
-            self.join_url(format!("datasets/create/version/{}", id))?,
-            Some(dataset_req),
-        )
-        .await
+    ) -> anyhow::Result<DatasetCreated> {
+        let resp: DatasetNewVersionResponse = self
+            .post_json(
+                self.join_url(format!("datasets/create/version/{}", id))?,
+                Some(dataset_req),
+            )
+            .await?;
+        Ok(resp.into_result()?)
     }
 
-    /// Download all files of a dataset.
+    /// Compare the dataset `folder` with the latest version of the remote
+    /// dataset without uploading anything.
     ///
-    /// Example
+    /// Shorthand for [`KaggleApiClient::dataset_version_plan_with`] with the
+    /// default upload options and without hashing the files.
     ///
-    /// Download the newest version of the whole `unanimad/dataisbeautiful`
-    /// dataset as zip file into
-    /// `<download-dir>/datasets/unanimad/dataisbeautiful.zip`
+    /// Example
     ///
     /// ```no_run
     /// use kaggle::KaggleApiClient;
@@ -1166,28 +1679,227 @@ impl KaggleApiClient {
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ///     let kaggle = KaggleApiClient::builder().build()?;
-    ///     let resp = kaggle
-    ///         .dataset_download_all_files("unanimad/dataisbeautiful", None, None)
-    ///         .await?;
+    ///     let plan = kaggle.dataset_version_plan(".").await?;
+    ///     println!("{}", serde_json::to_string_pretty(&plan)?);
+    ///     if !plan.is_empty() {
+    ///         kaggle
+    ///             .dataset_create_version_from_plan(&plan, "Updated data", false, false)
+    ///             .await?;
+    ///     }
     ///     Ok(())
     /// }
     /// ```
-    pub async fn dataset_download_all_files(
+    pub async fn dataset_version_plan(
         &self,
-        name: impl AsRef<str>,
-        path: Option<PathBuf>,
-        dataset_version_number: Option<&str>,
-    ) -> anyhow::Result<PathBuf> {
-        let (owner_slug, dataset_slug) = self.get_user_and_identifier_slug(name.as_ref())?;
+        folder: impl AsRef<Path>,
+    ) -> anyhow::Result<DatasetVersionPlan> {
+        self.dataset_version_plan_with(folder, UploadOptions::default(), None)
+            .await
+    }
 
-        let mut req = self
-            .client
-            .get(self.join_url(format!("datasets/download/{}/{}", owner_slug, dataset_slug))?)
-            .header(header::ACCEPT, HeaderValue::from_static("file"));
+    /// Compare the files that [`KaggleApiClient::dataset_create_version`]
+    /// would upload with the files of the latest remote version.
+    ///
+    /// Files are compared by name and size. If `known_hashes` is set, the
+    /// SHA-256 digest of every local file is recorded in the plan and files
+    /// with equal size but a digest that differs from the known digest of the
+    /// remote file are changed as well. Directories that are archived are
+    /// compared by the names and sizes of their files with the remote files
+    /// that Kaggle extracted from the previous archive.
+    ///
+    /// The `upload_options` are kept in the plan and used by
+    /// [`KaggleApiClient::dataset_create_version_from_plan`].
+    pub async fn dataset_version_plan_with(
+        &self,
+        folder: impl AsRef<Path>,
+        upload_options: impl Into<UploadOptions>,
+        known_hashes: Option<&HashMap<String, String>>,
+    ) -> anyhow::Result<DatasetVersionPlan> {
+        let folder = folder.as_ref();
+        let meta_data = Self::read_dataset_metadata_file(folder).await?;
+        let options = upload_options.into();
+        let local =
+            Self::planned_files(folder, &meta_data, &options, known_hashes.is_some()).await?;
 
-        if let Some(version) = dataset_version_number {
-            req = req.query(&[("datasetVersionNumber", version)]);
-        }
+        let remote = self.datasets_list_all_files(&meta_data.id).await?;
+        let remote_meta = self.metadata_get(&meta_data.id).await?;
+
+        Ok(
+            DatasetVersionPlan::new(&meta_data.id, folder, local, &remote, known_hashes)
+                .with_metadata(&meta_data, &remote_meta.info)
+                .with_upload_options(options),
+        )
+    }
+
+    /// The files of the `folder` that are uploaded with the `options`,
+    /// optionally with their SHA-256 digests.
+    async fn planned_files(
+        folder: &Path,
+        meta_data: &DatasetMetadataFile,
+        options: &UploadOptions,
+        hash: bool,
+    ) -> anyhow::Result<Vec<PlannedFile>> {
+        let filter = options.ignore_filter(folder)?;
+        let mut included = None;
+        let mut local = Vec::new();
+        for entry in options.entries(folder, &meta_data.resources, &filter)? {
+            let file = if entry.path.is_dir() {
+                let name = options
+                    .archive_mode
+                    .archive_name(&entry.name, &options.archive_options)
+                    .ok_or_else(|| KaggleError::FileNotFound(entry.path.clone()))?;
+                if included.is_none() {
+                    included = Some(filter.files()?);
+                }
+                let dir = entry.path.strip_prefix(folder)?;
+                let mut members = BTreeMap::new();
+                for file in included.iter().flatten() {
+                    if let Ok(member) = file.strip_prefix(dir) {
+                        let member = relative_name(member)
+                            .ok_or_else(|| anyhow!("Invalid file name {}", file.display()))?;
+                        members.insert(
+                            format!("{}/{}", entry.name, member),
+                            fs::metadata(folder.join(file))?.len(),
+                        );
+                    }
+                }
+                PlannedFile {
+                    name,
+                    path: Some(entry.path),
+                    local_bytes: None,
+                    remote_bytes: None,
+                    sha256: None,
+                    directory: Some(entry.name),
+                    members,
+                }
+            } else {
+                let sha256 = if hash {
+                    let path = entry.path.clone();
+                    Some(
+                        tokio::task::spawn_blocking(move || crate::archive::sha256_digest(path))
+                            .await??,
+                    )
+                } else {
+                    None
+                };
+                PlannedFile {
+                    name: entry.name,
+                    local_bytes: Some(fs::metadata(&entry.path)?.len()),
+                    path: Some(entry.path),
+                    remote_bytes: None,
+                    sha256,
+                    directory: None,
+                    members: BTreeMap::new(),
+                }
+            };
+            local.push(file);
+        }
+        Ok(local)
+    }
+
+    /// Create a new version of the dataset from a reviewed plan, using the
+    /// plan's upload options.
+    ///
+    /// Fails without uploading anything if the options now select other
+    /// files than planned or a local file changed since the plan was created,
+    /// see [`DatasetVersionPlan::verify_files`] and
+    /// [`DatasetVersionPlan::verify`].
+    pub async fn dataset_create_version_from_plan(
+        &self,
+        plan: &DatasetVersionPlan,
+        version_notes: impl ToString,
+        convert_to_csv: bool,
+        delete_old_versions: bool,
+    ) -> anyhow::Result<DatasetCreated> {
+        let meta_data = Self::read_dataset_metadata_file(&plan.folder).await?;
+        let local =
+            Self::planned_files(&plan.folder, &meta_data, &plan.upload_options, false).await?;
+        plan.verify_files(&local)?;
+        plan.verify()?;
+        self.dataset_create_version(
+            &plan.folder,
+            version_notes,
+            convert_to_csv,
+            delete_old_versions,
+            plan.upload_options.clone(),
+        )
+        .await
+    }
+
+    /// Preview which files of the dataset `folder` would be uploaded by
+    /// [`KaggleApiClient::dataset_create_version`] with the given options.
+    ///
+    /// Returns the paths relative to the `folder`, files inside directories
+    /// that would be archived are listed individually.
+    ///
+    /// Example
+    ///
+    /// ```no_run
+    /// use kaggle::upload::UploadOptions;
+    /// use kaggle::KaggleApiClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let files = KaggleApiClient::dataset_upload_preview(
+    ///         ".",
+    ///         UploadOptions::default().ignore_pattern("*.tmp"),
+    ///     )
+    ///     .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn dataset_upload_preview(
+        folder: impl AsRef<Path>,
+        upload_options: impl Into<UploadOptions>,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let folder = folder.as_ref();
+        let meta_data = Self::read_dataset_metadata_file(folder).await?;
+        upload_options.into().preview(folder, &meta_data.resources)
+    }
+
+    /// Download all files of a dataset.
+    ///
+    /// The version of a reference like `owner/slug/versions/3` is downloaded
+    /// unless a `dataset_version_number` is set.
+    ///
+    /// Example
+    ///
+    /// Download the newest version of the whole `unanimad/dataisbeautiful`
+    /// dataset as zip file into
+    /// `<download-dir>/datasets/unanimad/dataisbeautiful.zip`
+    ///
+    /// ```no_run
+    /// use kaggle::KaggleApiClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let kaggle = KaggleApiClient::builder().build()?;
+    ///     let resp = kaggle
+    ///         .dataset_download_all_files("unanimad/dataisbeautiful", None, None)
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn dataset_download_all_files(
+        &self,
+        name: impl IntoRef<DatasetRef>,
+        path: Option<PathBuf>,
+        dataset_version_number: Option<&str>,
+    ) -> anyhow::Result<PathBuf> {
+        let dataset = self.dataset_ref(name)?;
+        let (owner_slug, dataset_slug) = (dataset.owner(), dataset.slug());
+
+        let mut req = self
+            .client
+            .get(self.join_url(format!("datasets/download/{}/{}", owner_slug, dataset_slug))?)
+            .header(header::ACCEPT, HeaderValue::from_static("file"));
+
+        let version = dataset_version_number
+            .map(str::to_string)
+            .or_else(|| dataset.version_number().map(|v| v.to_string()));
+        if let Some(version) = version {
+            req = req.query(&[("datasetVersionNumber", version)]);
+        }
 
         let folder = path.unwrap_or_else(|| {
             self.download_dir
@@ -1207,14 +1919,18 @@ impl KaggleApiClient {
     }
 
     /// Download a single file for a dataset.
+    ///
+    /// The version of a reference like `owner/slug/versions/3` is downloaded
+    /// unless a `dataset_version_number` is set.
     pub async fn dataset_download_file(
         &self,
-        name: impl AsRef<str>,
+        name: impl IntoRef<DatasetRef>,
         file_name: impl AsRef<str>,
         folder: Option<PathBuf>,
         dataset_version_number: Option<&str>,
     ) -> anyhow::Result<PathBuf> {
-        let (owner_slug, dataset_slug) = self.get_user_and_identifier_slug(name.as_ref())?;
+        let dataset = self.dataset_ref(name)?;
+        let (owner_slug, dataset_slug) = (dataset.owner(), dataset.slug());
 
         let mut req = self
             .client
@@ -1226,7 +1942,10 @@ impl KaggleApiClient {
             ))?)
             .header(header::ACCEPT, HeaderValue::from_static("file"));
 
-        if let Some(version) = dataset_version_number {
+        let version = dataset_version_number
+            .map(str::to_string)
+            .or_else(|| dataset.version_number().map(|v| v.to_string()));
+        if let Some(version) = version {
             req = req.query(&[("datasetVersionNumber", version)]);
         }
 
@@ -1236,7 +1955,7 @@ impl KaggleApiClient {
             .url()
             .path_segments()
             .context("redirected to invalid dataset download url")?
-            .last()
+            .next_back()
             .context("no file segment in url download path")?;
 
         let output = folder.unwrap_or_else(|| {
@@ -1319,26 +2038,149 @@ impl KaggleApiClient {
     /// ```
     pub async fn datasets_list_files(
         &self,
-        name: impl AsRef<str>,
+        name: impl IntoRef<DatasetRef>,
     ) -> anyhow::Result<ListFilesResult> {
-        let (owner_slug, dataset_slug) = self.get_user_and_identifier_slug(name.as_ref())?;
-        Self::request_json(
-            self.client
-                .get(self.join_url(format!("datasets/list/{}/{}", owner_slug, dataset_slug))?),
-        )
-        .await
+        self.datasets_list_files_page(name, None).await
+    }
+
+    /// List a page of the files of a dataset, the `page_token` is the
+    /// `next_page_token` of the previous page.
+    pub async fn datasets_list_files_page(
+        &self,
+        name: impl IntoRef<DatasetRef>,
+        page_token: Option<&str>,
+    ) -> anyhow::Result<ListFilesResult> {
+        let dataset = self.dataset_ref(name)?;
+        let (owner_slug, dataset_slug) = (dataset.owner(), dataset.slug());
+        let mut url = self.join_url(format!("datasets/list/{}/{}", owner_slug, dataset_slug))?;
+        if let Some(page_token) = page_token {
+            url.query_pairs_mut().append_pair("pageToken", page_token);
+        }
+        self.get_json(url).await
+    }
+
+    /// List the files of all pages of a dataset.
+    pub async fn datasets_list_all_files(
+        &self,
+        name: impl IntoRef<DatasetRef>,
+    ) -> anyhow::Result<Vec<DatasetFile>> {
+        let dataset = self.dataset_ref(name)?;
+        let mut files = Vec::new();
+        let mut page_token = None;
+        loop {
+            let page = self
+                .datasets_list_files_page(&dataset, page_token.as_deref())
+                .await?;
+            if let Some(msg) = page.error_message.filter(|msg| !msg.is_empty()) {
+                return Err(anyhow!(msg));
+            }
+            files.extend(page.dataset_files);
+            match page.next_page_token {
+                Some(token) if !token.is_empty() => page_token = Some(token),
+                _ => break,
+            }
+        }
+        Ok(files)
     }
 
     /// Get dataset creation status.
     pub async fn datasets_status(
         &self,
-        name: impl AsRef<str>,
-    ) -> anyhow::Result<Option<serde_json::Value>> {
-        let (owner_slug, dataset_slug) = self.get_user_and_identifier_slug(name.as_ref())?;
+        name: impl IntoRef<DatasetRef>,
+    ) -> anyhow::Result<DatasetStatus> {
+        let dataset = self.dataset_ref(name)?;
+        Ok(DatasetStatus::from_value(
+            &self.datasets_status_value(&dataset).await?,
+        ))
+    }
+
+    /// The raw response of the `datasets/status` endpoint.
+    async fn datasets_status_value(
+        &self,
+        dataset: &DatasetRef,
+    ) -> anyhow::Result<serde_json::Value> {
+        let (owner_slug, dataset_slug) = (dataset.owner(), dataset.slug());
         self.get_json(self.join_url(format!("datasets/status/{}/{}", owner_slug, dataset_slug))?)
             .await
     }
 
+    /// Poll the status of the dataset every `poll_interval` until processing
+    /// of the latest version finished.
+    ///
+    /// Pass the `created` version after creating a dataset or a new version,
+    /// so a status that still describes the previous version is ignored.
+    /// Only the status of the created version counts then or, if the server
+    /// reports no version numbers, a finished status once processing was
+    /// seen pending.
+    ///
+    /// Returns a [`KaggleError::DatasetFailed`] if processing failed and a
+    /// [`KaggleError::Timeout`] if the dataset is not ready after `timeout`.
+    ///
+    /// Example
+    ///
+    /// ```no_run
+    /// use kaggle::archive::ArchiveMode;
+    /// use kaggle::KaggleApiClient;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let kaggle = KaggleApiClient::builder().build()?;
+    ///     let created = kaggle
+    ///         .dataset_create_version(".", "new version", true, false, ArchiveMode::Skip)
+    ///         .await?;
+    ///     kaggle
+    ///         .wait_for_dataset_ready(
+    ///             &created.ref_,
+    ///             Some(&created),
+    ///             Duration::from_secs(600),
+    ///             Duration::from_secs(10),
+    ///         )
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn wait_for_dataset_ready(
+        &self,
+        name: impl IntoRef<DatasetRef>,
+        created: Option<&DatasetCreated>,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> anyhow::Result<DatasetStatus> {
+        let name = self.dataset_ref(name)?;
+        let mut new_version = created.map(|created| {
+            NewVersionStatus::new(created.version_number.and_then(|v| v.try_into().ok()))
+        });
+        poll_until(
+            &format!("dataset {}", name),
+            timeout,
+            poll_interval,
+            || self.datasets_status_value(&name),
+            |value| {
+                let status = DatasetStatus::from_value(&value);
+                if let Some(new_version) = &mut new_version {
+                    if !new_version.is_current(&value, status.is_finished()) {
+                        debug!("Dataset {} still reports the previous version", name);
+                        return Ok(None);
+                    }
+                }
+                match status {
+                    DatasetStatus::Ready => Ok(Some(DatasetStatus::Ready)),
+                    DatasetStatus::Error(msg) => Err(KaggleError::DatasetFailed {
+                        dataset: name.to_string(),
+                        msg,
+                    }
+                    .into()),
+                    status => {
+                        debug!("Dataset {} is {:?}", name, status);
+                        Ok(None)
+                    }
+                }
+            },
+        )
+        .await
+    }
+
     /// Get URL and token to start uploading a data file.
     pub async fn datasets_upload_file(
         &self,
@@ -1361,74 +2203,194 @@ impl KaggleApiClient {
     }
 
     /// Show details about a dataset.
-    pub async fn datasets_view(&self, name: impl AsRef<str>) -> anyhow::Result<Dataset> {
-        let (owner_slug, dataset_slug) = self.get_user_and_identifier_slug(name.as_ref())?;
+    pub async fn datasets_view(&self, name: impl IntoRef<DatasetRef>) -> anyhow::Result<Dataset> {
+        let dataset = self.dataset_ref(name)?;
+        let (owner_slug, dataset_slug) = (dataset.owner(), dataset.slug());
         self.get_json(self.join_url(format!("datasets/view/{}/{}", owner_slug, dataset_slug))?)
             .await
     }
 
-    /// Retrieve output for a specified kernel.
+    /// Download the output files and the log of the latest run of a kernel.
+    ///
+    /// Files are listed page by page and those that match the request's
+    /// patterns are downloaded, up to `request.concurrency` at the same time.
+    /// The log is written to `<kernel-slug>.log`.
     pub async fn kernels_output(
         &self,
-        name: impl AsRef<str>,
-        path: Option<PathBuf>,
+        request: KernelOutputRequest,
     ) -> anyhow::Result<Vec<PathBuf>> {
-        let name = name.as_ref();
-        let (owner_slug, kernel_slug) = self.get_user_and_identifier_slug(name)?;
+        let kernel = self.kernel_ref(&request.name)?;
+        let (owner_slug, kernel_slug) = (kernel.owner(), kernel.slug());
 
-        let folder = path.unwrap_or_else(|| {
+        let folder = request.output.clone().unwrap_or_else(|| {
             self.download_dir
                 .join(format!("datasets/{}/{}/output", owner_slug, kernel_slug,))
         });
 
         fs::create_dir_all(&folder)?;
 
-        let resp = self.kernel_output(name).await?;
-
-        let mut outfiles = Vec::with_capacity(resp.files.len());
+        let filter = request.file_filter()?;
+        let mut outfiles = Vec::new();
+        let mut log = None;
+        let mut page_token = None;
+
+        loop {
+            let resp = self
+                .kernel_output_page(&kernel, request.page_size, page_token.as_deref())
+                .await?;
+            log = log.or(resp.log);
+
+            let mut outstream = stream::iter(
+                resp.files
+                    .into_iter()
+                    .filter(|file| filter.is_match(&file.file_name))
+                    .map(|file| self.download_output_file(file, &folder, &request)),
+            )
+            .buffer_unordered(request.concurrency.max(1));
 
-        let mut outstream = stream::iter(resp.files.into_iter().map(|file| async {
-            let outfile = folder.join(file.file_name);
-            let content = file.url.content;
-            tokio::fs::write(&outfile, content).await?;
-            Ok::<_, std::io::Error>(outfile)
-        }))
-        .buffer_unordered(3);
+            while let Some(f) = outstream.next().await {
+                outfiles.push(f?);
+            }
 
-        while let Some(f) = outstream.next().await {
-            outfiles.push(f?);
+            match resp.next_page_token {
+                Some(token) if !token.is_empty() => page_token = Some(token),
+                _ => break,
+            }
         }
 
-        if let Some(log) = resp.log {
-            let outfile = folder.join(format!("{}.log", kernel_slug));
+        let log_name = format!("{}.log", kernel_slug);
+        if let Some(log) = log.filter(|_| filter.is_match(&log_name)) {
+            let outfile = folder.join(log_name);
             tokio::fs::write(&outfile, log).await?;
             outfiles.push(outfile);
         }
         Ok(outfiles)
     }
 
-    /// RDownload the latest output from a kernel
-    pub async fn kernel_output(&self, name: impl AsRef<str>) -> anyhow::Result<KernelOutput> {
-        let (owner_slug, kernel_slug) = self.get_user_and_identifier_slug(name.as_ref())?;
+    /// Write a single output file into `folder`, either by streaming it from
+    /// its download URL or from its inline content.
+    async fn download_output_file(
+        &self,
+        file: KernelOutputFile,
+        folder: &Path,
+        request: &KernelOutputRequest,
+    ) -> anyhow::Result<PathBuf> {
+        let relative = Path::new(&file.file_name);
+        if !relative
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+        {
+            return Err(
+                KaggleError::meta(format!("Invalid output file name {}", file.file_name)).into(),
+            );
+        }
+        let outfile = folder.join(relative);
+        if let Some(parent) = outfile.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let report = |downloaded, total| {
+            if let Some(progress) = &request.progress {
+                progress(DownloadProgress {
+                    file_name: &file.file_name,
+                    downloaded,
+                    total,
+                });
+            }
+        };
 
-        if kernel_slug.len() < 5 {
-            return Err(KaggleError::meta(format!(
-                "Kernel slug `{}` must be at least five characters.",
-                kernel_slug
-            ))
-            .into());
+        // write to a temporary name, so a failed download never leaves a
+        // truncated file or replaces the output of an earlier download
+        let mut partial_name = outfile.file_name().unwrap_or_default().to_os_string();
+        partial_name.push(".part");
+        let partial = outfile.with_file_name(partial_name);
+
+        let written = async {
+            match file.url.url() {
+                Some(url) => {
+                    debug!("Downloading {} from {}", file.file_name, url);
+                    // signed download URLs must not carry the Kaggle credentials
+                    let mut res = Self::request(self.storage_client.get(url)).await?;
+                    let total = res.content_length();
+                    let mut out = tokio::fs::File::create(&partial).await?;
+                    let mut downloaded = 0;
+                    while let Some(chunk) = res.chunk().await? {
+                        out.write_all(&chunk).await?;
+                        downloaded += chunk.len() as u64;
+                        report(downloaded, total);
+                    }
+                    out.flush().await?;
+                }
+                None => {
+                    let len = file.url.content.len() as u64;
+                    tokio::fs::write(&partial, file.url.content.as_bytes()).await?;
+                    report(len, Some(len));
+                }
+            }
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        if let Err(err) = written {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(err);
         }
+        tokio::fs::rename(&partial, &outfile).await?;
+        Ok(outfile)
+    }
 
-        self.get_json(self.join_url(format!(
-            "kernels/output?userName={}&kernelSlug={}",
-            owner_slug, kernel_slug
-        ))?)
-        .await
+    /// Retrieve the first page of the output of the latest run of a kernel.
+    pub async fn kernel_output(
+        &self,
+        name: impl IntoRef<KernelRef>,
+    ) -> anyhow::Result<KernelOutput> {
+        self.kernel_output_page(name, None, None).await
+    }
+
+    /// Retrieve and parse the log of the latest run of a kernel, `None` if
+    /// the run has no log yet.
+    pub async fn kernel_log(
+        &self,
+        name: impl IntoRef<KernelRef>,
+    ) -> anyhow::Result<Option<KernelLog>> {
+        match self.kernel_output(name).await?.log {
+            Some(log) if !log.trim().is_empty() => Ok(Some(log.parse()?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Retrieve a page of the output of the latest run of a kernel, the
+    /// `page_token` is the `next_page_token` of the previous page.
+    pub async fn kernel_output_page(
+        &self,
+        name: impl IntoRef<KernelRef>,
+        page_size: Option<u32>,
+        page_token: Option<&str>,
+    ) -> anyhow::Result<KernelOutput> {
+        let kernel = self.kernel_ref(name)?;
+        let (owner_slug, kernel_slug) = (kernel.owner(), kernel.slug());
+
+        let mut url = self.join_url("kernels/output")?;
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("userName", owner_slug)
+                .append_pair("kernelSlug", kernel_slug);
+            if let Some(page_size) = page_size {
+                query.append_pair("pageSize", &page_size.to_string());
+            }
+            if let Some(page_token) = page_token {
+                query.append_pair("pageToken", page_token);
+            }
+        }
+        self.get_json(url).await
     }
 
     /// Pull the latest code from a kernel.
-    pub async fn kernel_pull(&self, name: impl AsRef<str>) -> anyhow::Result<KernelPullResponse> {
-        let (owner_slug, kernel_slug) = self.get_user_and_identifier_slug(name.as_ref())?;
+    pub async fn kernel_pull(
+        &self,
+        name: impl IntoRef<KernelRef>,
+    ) -> anyhow::Result<KernelPullResponse> {
+        let kernel = self.kernel_ref(name)?;
+        let (owner_slug, kernel_slug) = (kernel.owner(), kernel.slug());
         self.get_json(self.join_url(format!(
             "kernels/pull?userName={}&kernelSlug={}",
             owner_slug, kernel_slug
@@ -1442,9 +2404,10 @@ impl KaggleApiClient {
         &self,
         pull: KernelPullRequest,
     ) -> anyhow::Result<(PathBuf, Option<PathBuf>)> {
-        let (owner_slug, kernel_slug) = self.get_user_and_identifier_slug(&pull.name)?;
+        let kernel = self.kernel_ref(&pull.name)?;
+        let (owner_slug, kernel_slug) = (kernel.owner(), kernel.slug());
 
-        let resp = self.kernel_pull(&pull.name).await?;
+        let resp = self.kernel_pull(&kernel).await?;
 
         let folder = pull.output.unwrap_or_else(|| {
             self.download_dir
@@ -1456,7 +2419,8 @@ impl KaggleApiClient {
 
         let file_name = if metadata_path.exists() {
             let existing_meta = Self::read_kernel_metadata_file(&metadata_path).await?;
-            if Some("INSERT_CODE_FILE_PATH_HERE") == existing_meta.code_file.as_deref() {
+            if Some(KernelMetadataFile::CODE_FILE_PLACEHOLDER) == existing_meta.code_file.as_deref()
+            {
                 None
             } else {
                 existing_meta.code_file
@@ -1466,16 +2430,18 @@ impl KaggleApiClient {
         }
         .unwrap_or_else(|| "script.py".to_string());
 
-        let output = folder.join(file_name);
+        let output = folder.join(&file_name);
 
-        tokio::fs::write(&output, resp.blob.source).await?;
+        let source = if PushKernelType::Notebook == resp.blob.kernel_type {
+            Self::clean_pulled_notebook(&resp.blob.source, &output).unwrap_or(resp.blob.source)
+        } else {
+            resp.blob.source
+        };
+        tokio::fs::write(&output, source).await?;
 
         if pull.with_metadata {
-            tokio::fs::write(
-                &metadata_path,
-                serde_json::to_string_pretty(&resp.metadata)?,
-            )
-            .await?;
+            let metadata = KernelMetadataFile::from_remote(resp.metadata, file_name);
+            tokio::fs::write(&metadata_path, serde_json::to_string_pretty(&metadata)?).await?;
 
             Ok((output, Some(metadata_path)))
         } else {
@@ -1483,6 +2449,26 @@ impl KaggleApiClient {
         }
     }
 
+    /// Strip a pulled notebook and render it as notebook file or, if `output`
+    /// is not a notebook file, as percent-format script.
+    ///
+    /// Returns `None` if the source is not a valid nbformat v4 notebook.
+    fn clean_pulled_notebook(source: &str, output: &Path) -> Option<String> {
+        let mut notebook: Notebook = match source.parse() {
+            Ok(notebook) => notebook,
+            Err(err) => {
+                debug!("Keeping pulled notebook as is: {}", err);
+                return None;
+            }
+        };
+        notebook.strip(&StripOptions::default());
+        if Notebook::is_notebook_file(output) {
+            notebook.to_json().ok()
+        } else {
+            Some(notebook.to_percent_script())
+        }
+    }
+
     /// read the metadata file and kernel files from a notebook, validate both,
     /// and use Kernel API to push to Kaggle if all is valid.
     pub async fn kernels_push(
@@ -1491,59 +2477,26 @@ impl KaggleApiClient {
     ) -> anyhow::Result<KernelPushResponse> {
         let folder = folder.as_ref();
         let metadata = Self::read_kernel_metadata_file(folder).await?;
+        metadata.validate(folder).into_result()?;
 
-        if metadata.title.len() < 5 {
-            return Err(KaggleError::meta("Title must be at least five characters").into());
-        }
-
-        metadata.is_dataset_sources_valid()?;
-        metadata.is_kernel_sources_valid()?;
-
-        let code_path = metadata
-            .code_file
-            .ok_or_else(|| KaggleError::meta("A source file must be specified in the metadata"))?;
-
-        let code_file = folder.join(code_path);
-        if !code_file.is_file() && !code_file.exists() {
-            return Err(KaggleError::meta(format!(
-                "Source file not found:{}",
-                code_file.display()
-            ))
-            .into());
-        }
-
-        let (_owner_slug, kernel_slug) = self
-            .get_user_and_identifier_slug(&metadata.id)
-            .map(|(s1, s2)| (s1.to_string(), s2.to_string()))?;
-
-        if kernel_slug.to_lowercase() != slug::slugify(&metadata.title) {
-            return Err(
-                KaggleError::meta("kernel title does not resolve to the specified id").into(),
-            );
-        }
+        let code_file = folder.join(
+            metadata
+                .code_file
+                .as_deref()
+                .context("A source file must be specified in the metadata")?,
+        );
 
         let script_body = tokio::fs::read(&code_file).await?;
 
         let text = if Some(PushKernelType::Notebook) == metadata.kernel_type {
-            let mut json_body = serde_json::from_slice::<serde_json::Value>(&script_body)?;
-
-            // clean outputs
-            let obj = json_body
-                .as_object_mut()
-                .context("Expected json object in code file")?;
-            if let Some(cells) = obj.get_mut("cells").and_then(|x| x.as_array_mut()) {
-                for cell in cells {
-                    if let Some(cell_obj) = cell.as_object_mut() {
-                        if cell_obj.contains_key("outputs")
-                            && Some("code") == cell_obj.get("cell_type").and_then(|x| x.as_str())
-                        {
-                            cell_obj
-                                .insert("outputs".to_string(), serde_json::Value::Array(vec![]));
-                        }
-                    }
-                }
-            }
-            serde_json::to_string(&json_body)?
+            let script_body = String::from_utf8(script_body)?;
+            let mut notebook = if Notebook::is_notebook_file(&code_file) {
+                script_body.parse()?
+            } else {
+                Notebook::from_percent_script(&script_body)
+            };
+            notebook.strip(&StripOptions::default());
+            serde_json::to_string(&notebook)?
         } else {
             String::from_utf8_lossy(&script_body).to_string()
         };
@@ -1596,9 +2549,23 @@ impl KaggleApiClient {
             .await
     }
 
-    /// Get the status of a kernel.
-    pub async fn kernel_status(&self, name: impl AsRef<str>) -> anyhow::Result<serde_json::Value> {
-        let (owner_slug, kernel_slug) = self.get_user_and_identifier_slug(name.as_ref())?;
+    /// Get the status of the latest run of a kernel.
+    pub async fn kernel_status(
+        &self,
+        name: impl IntoRef<KernelRef>,
+    ) -> anyhow::Result<KernelStatus> {
+        Ok(KernelStatus::from_value(
+            &self.kernel_status_value(name).await?,
+        ))
+    }
+
+    /// The raw response of the `kernels/status` endpoint.
+    async fn kernel_status_value(
+        &self,
+        name: impl IntoRef<KernelRef>,
+    ) -> anyhow::Result<serde_json::Value> {
+        let kernel = self.kernel_ref(name)?;
+        let (owner_slug, kernel_slug) = (kernel.owner(), kernel.slug());
         Self::request_json(self.client.get(self.join_url(format!(
             "kernels/status?userName={}&kernelSlug={}",
             owner_slug, kernel_slug
@@ -1606,6 +2573,99 @@ impl KaggleApiClient {
         .await
     }
 
+    /// Poll the status of the kernel every `poll_interval` until its latest
+    /// run ended.
+    ///
+    /// Returns a [`KaggleError::KernelFailed`] if the run failed or was
+    /// cancelled and a [`KaggleError::Timeout`] if it is still running after
+    /// `timeout`.
+    pub async fn wait_for_kernel(
+        &self,
+        name: impl IntoRef<KernelRef>,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> anyhow::Result<KernelStatus> {
+        let name = self.kernel_ref(name)?;
+        poll_until(
+            &format!("kernel {}", name),
+            timeout,
+            poll_interval,
+            || self.kernel_status(&name),
+            |status| kernel_run_result(&name, status),
+        )
+        .await
+    }
+
+    /// Push the kernel in `folder`, wait until the new run ended and
+    /// optionally download its output with
+    /// [`KaggleApiClient::kernels_output`].
+    ///
+    /// The wait only ends on a finished run of the pushed version. If the
+    /// server doesn't report versions, a run that was already finished when
+    /// the push returned is ignored until the new run was seen queued or
+    /// running.
+    ///
+    /// Returns the push response and the downloaded output files.
+    ///
+    /// Example
+    ///
+    /// ```no_run
+    /// use kaggle::KaggleApiClient;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let kaggle = KaggleApiClient::builder().build()?;
+    ///     let (_, outputs) = kaggle
+    ///         .push_and_wait(".", Duration::from_secs(3600), Duration::from_secs(30), true)
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn push_and_wait(
+        &self,
+        folder: impl AsRef<Path>,
+        timeout: Duration,
+        poll_interval: Duration,
+        download_output: bool,
+    ) -> anyhow::Result<(KernelPushResponse, Vec<PathBuf>)> {
+        let folder = folder.as_ref();
+        let metadata = Self::read_kernel_metadata_file(folder).await?;
+        let kernel = self.kernel_ref(&metadata.id)?;
+        let resp = self.kernels_push(folder).await?;
+        if let Some(err) = resp.error() {
+            return Err(KaggleError::KernelFailed {
+                kernel: kernel.to_string(),
+                msg: err.to_string(),
+            }
+            .into());
+        }
+        let kernel = resp.kernel().unwrap_or(kernel);
+        let mut pushed = NewVersionStatus::new(resp.version_number().map(u64::from));
+        poll_until(
+            &format!("kernel {}", kernel),
+            timeout,
+            poll_interval,
+            || self.kernel_status_value(&kernel),
+            |value| {
+                let status = KernelStatus::from_value(&value);
+                if !pushed.is_current(&value, status.is_finished()) {
+                    debug!("Kernel {} still reports the previous run", kernel);
+                    return Ok(None);
+                }
+                kernel_run_result(&kernel, status)
+            },
+        )
+        .await?;
+        let outputs = if download_output {
+            self.kernels_output(KernelOutputRequest::new(&kernel))
+                .await?
+        } else {
+            Vec::new()
+        };
+        Ok((resp, outputs))
+    }
+
     /// List kernels based on a set of search criteria.
     ///
     /// Example
@@ -1633,8 +2693,12 @@ impl KaggleApiClient {
     }
 
     /// Get the metadata for a dataset.
-    pub async fn metadata_get(&self, name: impl AsRef<str>) -> anyhow::Result<DatasetMetadata> {
-        let (owner_slug, dataset_slug) = self.get_user_and_identifier_slug(name.as_ref())?;
+    pub async fn metadata_get(
+        &self,
+        name: impl IntoRef<DatasetRef>,
+    ) -> anyhow::Result<DatasetMetadata> {
+        let dataset = self.dataset_ref(name)?;
+        let (owner_slug, dataset_slug) = (dataset.owner(), dataset.slug());
         Self::request_json(
             self.client
                 .get(self.join_url(format!("datasets/metadata/{}/{}", owner_slug, dataset_slug))?),
@@ -1642,34 +2706,124 @@ impl KaggleApiClient {
         .await
     }
 
+    /// Download the metadata of a dataset into a `dataset-metadata.json` in
+    /// `path` and return the path of the file.
+    ///
+    /// Defaults to the dataset's folder in the download directory, which is
+    /// also where [`KaggleApiClient::dataset_metadata_update`] reads the
+    /// metadata from, so the file can be edited and uploaded again.
+    ///
+    /// Example
+    ///
+    /// ```no_run
+    /// use kaggle::KaggleApiClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let kaggle = KaggleApiClient::builder().build()?;
+    ///     let file = kaggle
+    ///         .dataset_metadata_download("mczielinski/bitcoin-historical-data", None)
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn dataset_metadata_download(
+        &self,
+        name: impl IntoRef<DatasetRef>,
+        path: Option<PathBuf>,
+    ) -> anyhow::Result<PathBuf> {
+        let dataset = self.dataset_ref(name)?;
+        let (owner_slug, dataset_slug) = (dataset.owner(), dataset.slug());
+        let folder = path.unwrap_or_else(|| {
+            self.download_dir
+                .join(format!("datasets/{}/{}", owner_slug, dataset_slug))
+        });
+        let mut metadata = DatasetMetadataFile::from(self.metadata_get(&dataset).await?.info);
+        // the metadata doesn't name the owner of datasets of organizations
+        metadata.id = format!("{}/{}", owner_slug, dataset_slug);
+        tokio::fs::create_dir_all(&folder).await?;
+        let file = folder.join(Self::DATASET_METADATA_FILE);
+        tokio::fs::write(&file, serde_json::to_string_pretty(&metadata)?).await?;
+        Ok(file)
+    }
+
     /// Update the metadata for a dataset
     pub async fn dataset_metadata_update(
         &self,
-        name: impl AsRef<str>,
+        name: impl IntoRef<DatasetRef>,
         path: Option<PathBuf>,
     ) -> anyhow::Result<serde_json::Value> {
-        let name = name.as_ref();
+        let dataset = self.dataset_ref(name)?;
         let metadata = if let Some(path) = path {
             Self::read_dataset_metadata_file(path).await?
         } else {
-            let (owner_slug, dataset_slug) = self.get_user_and_identifier_slug(name)?;
-            Self::read_dataset_metadata_file(
-                self.download_dir
-                    .join(format!("datasets/{}/{}", owner_slug, dataset_slug)),
-            )
+            Self::read_dataset_metadata_file(self.download_dir.join(format!(
+                "datasets/{}/{}",
+                dataset.owner(),
+                dataset.slug()
+            )))
             .await?
         };
 
         let settings = metadata.into();
-        self.metadata_post(name, &settings).await
+        self.metadata_post(dataset, &settings).await
+    }
+
+    /// Compare the `local` metadata with the settings of the remote dataset
+    /// `name`.
+    ///
+    /// Example
+    ///
+    /// ```no_run
+    /// use kaggle::models::metadata::DatasetMetadataFile;
+    /// use kaggle::KaggleApiClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let kaggle = KaggleApiClient::builder().build()?;
+    ///     let local: DatasetMetadataFile =
+    ///         serde_json::from_slice(&std::fs::read("dataset-metadata.json")?)?;
+    ///     let diff = kaggle.dataset_metadata_diff("me/weather-stations", &local).await?;
+    ///     for change in &diff.changes {
+    ///         println!("{}: {} -> {}", change.field, change.remote, change.local);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn dataset_metadata_diff(
+        &self,
+        name: impl IntoRef<DatasetRef>,
+        local: &DatasetMetadataFile,
+    ) -> anyhow::Result<MetadataDiff> {
+        let remote = self.metadata_get(name).await?;
+        Ok(MetadataDiff::new(local, &remote.info))
+    }
+
+    /// Update only the settings of the dataset `name` that differ from the
+    /// `local` metadata and return the differences.
+    ///
+    /// Nothing is sent if the metadata is unchanged.
+    pub async fn dataset_metadata_update_changes(
+        &self,
+        name: impl IntoRef<DatasetRef>,
+        local: &DatasetMetadataFile,
+    ) -> anyhow::Result<MetadataDiff> {
+        let dataset = self.dataset_ref(name)?;
+        let diff = self.dataset_metadata_diff(&dataset, local).await?;
+        if !diff.is_empty() {
+            self.metadata_post(dataset, &diff.update_request(local))
+                .await?;
+        }
+        Ok(diff)
     }
 
     pub async fn metadata_post(
         &self,
-        name: impl AsRef<str>,
+        name: impl IntoRef<DatasetRef>,
         settings: &DatasetUpdateSettingsRequest,
     ) -> anyhow::Result<serde_json::Value> {
-        let (owner_slug, dataset_slug) = self.get_user_and_identifier_slug(name.as_ref())?;
+        let dataset = self.dataset_ref(name)?;
+        let (owner_slug, dataset_slug) = (dataset.owner(), dataset.slug());
 
         self.post_json(
             self.join_url(format!("datasets/metadata/{}/{}", owner_slug, dataset_slug))?,
@@ -1679,16 +2833,12 @@ impl KaggleApiClient {
     }
 }
 
-fn into_bytes_stream<R>(r: R) -> impl Stream<Item = tokio::io::Result<Bytes>>
-where
-    R: AsyncRead,
-{
-    codec::FramedRead::new(r, codec::BytesCodec::new()).map_ok(|bytes| bytes.freeze())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::archive::{ArchiveMode, ArchiveOptions};
+    use wiremock::matchers::{body_string_contains, header, method, path, path_regex, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
 
     fn kaggle() -> KaggleApiClient {
         KaggleApiClient::builder()
@@ -1697,6 +2847,889 @@ mod tests {
             .unwrap()
     }
 
+    /// A client that sends all api requests to the mock `server`.
+    fn mock_kaggle(server: &MockServer) -> KaggleApiClient {
+        KaggleApiClient::builder()
+            .auth(Authentication::with_credentials("name", "key"))
+            .base_url(format!("{}/api/v1/", server.uri()).parse().unwrap())
+            .build()
+            .unwrap()
+    }
+
+    /// Mock the upload of the dataset file `name`, the file's content is
+    /// sent to `/upload/<name>`.
+    async fn mock_file_upload(server: &MockServer, name: &str) {
+        Mock::given(method("POST"))
+            .and(path_regex("^/api/v1/datasets/upload/file/"))
+            .and(body_string_contains(name))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "token": format!("token-{}", name),
+                "createUrl": format!("{}/upload/{}", server.uri(), name),
+            })))
+            .mount(server)
+            .await;
+    }
+
+    async fn requests_to(server: &MockServer, path: &str) -> usize {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|req| req.url.path() == path)
+            .count()
+    }
+
+    /// Mock the status endpoint at `status_path` to report `statuses` in
+    /// order, the last one is repeated.
+    async fn mock_statuses(server: &MockServer, status_path: &str, statuses: &[serde_json::Value]) {
+        for (idx, status) in statuses.iter().enumerate() {
+            let mock = Mock::given(method("GET"))
+                .and(path(status_path))
+                .respond_with(ResponseTemplate::new(200).set_body_json(status));
+            let mock = if idx + 1 < statuses.len() {
+                mock.up_to_n_times(1)
+            } else {
+                mock
+            };
+            mock.mount(server).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn wait_for_kernel_status_sequence() {
+        let server = MockServer::start().await;
+        let kaggle = mock_kaggle(&server);
+        let interval = Duration::from_millis(1);
+
+        mock_statuses(
+            &server,
+            "/api/v1/kernels/status",
+            &[
+                serde_json::json!({"status": "queued"}),
+                serde_json::json!({"status": "running"}),
+                serde_json::json!({"status": "complete"}),
+            ],
+        )
+        .await;
+        let status = kaggle
+            .wait_for_kernel("me/my-kernel", Duration::from_secs(10), interval)
+            .await
+            .unwrap();
+        assert_eq!(status, KernelStatus::Complete);
+        assert_eq!(requests_to(&server, "/api/v1/kernels/status").await, 3);
+
+        server.reset().await;
+        mock_statuses(
+            &server,
+            "/api/v1/kernels/status",
+            &[
+                serde_json::json!({"status": "running"}),
+                serde_json::json!({"status": "error", "failureMessage": "OOM"}),
+            ],
+        )
+        .await;
+        let err = kaggle
+            .wait_for_kernel("me/my-kernel", Duration::from_secs(10), interval)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<KaggleError>(),
+            Some(KaggleError::KernelFailed { msg, .. }) if msg == "OOM"
+        ));
+
+        server.reset().await;
+        mock_statuses(
+            &server,
+            "/api/v1/kernels/status",
+            &[serde_json::json!({"status": "running"})],
+        )
+        .await;
+        let err = kaggle
+            .wait_for_kernel("me/my-kernel", Duration::from_millis(20), interval)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<KaggleError>(),
+            Some(KaggleError::Timeout { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn wait_for_created_dataset_version() {
+        let server = MockServer::start().await;
+        let kaggle = mock_kaggle(&server);
+        let status_path = "/api/v1/datasets/status/me/data";
+        let interval = Duration::from_millis(1);
+        let created = |version_number| DatasetCreated {
+            ref_: "me/data".to_string(),
+            url: "https://www.kaggle.com/me/data".to_string(),
+            version_number,
+            invalid_tags: Vec::new(),
+        };
+
+        // the stale status of the previous version is skipped until the new
+        // version was seen pending
+        mock_statuses(
+            &server,
+            status_path,
+            &[
+                serde_json::json!("ready"),
+                serde_json::json!({"status": "error", "error": "previous failed"}),
+                serde_json::json!("pending"),
+                serde_json::json!("ready"),
+            ],
+        )
+        .await;
+        let status = kaggle
+            .wait_for_dataset_ready(
+                "me/data",
+                Some(&created(None)),
+                Duration::from_secs(10),
+                interval,
+            )
+            .await
+            .unwrap();
+        assert_eq!(status, DatasetStatus::Ready);
+        assert_eq!(requests_to(&server, status_path).await, 4);
+
+        // with version numbers only the created version counts
+        server.reset().await;
+        mock_statuses(
+            &server,
+            status_path,
+            &[
+                serde_json::json!({"status": "ready", "versionNumber": 2}),
+                serde_json::json!({"status": "ready", "versionNumber": 3}),
+            ],
+        )
+        .await;
+        kaggle
+            .wait_for_dataset_ready(
+                "me/data",
+                Some(&created(Some(3))),
+                Duration::from_secs(10),
+                interval,
+            )
+            .await
+            .unwrap();
+        assert_eq!(requests_to(&server, status_path).await, 2);
+
+        // without a created version any finished status counts
+        server.reset().await;
+        mock_statuses(&server, status_path, &[serde_json::json!("ready")]).await;
+        kaggle
+            .wait_for_dataset_ready("me/data", None, Duration::from_secs(10), interval)
+            .await
+            .unwrap();
+        assert_eq!(requests_to(&server, status_path).await, 1);
+    }
+
+    #[tokio::test]
+    async fn push_and_wait_ignores_the_previous_run() {
+        let server = MockServer::start().await;
+        let kaggle = mock_kaggle(&server);
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("run.py"), "print(1)").unwrap();
+        fs::write(
+            dir.path().join(KernelMetadataFile::FILE_NAME),
+            serde_json::json!({
+                "id": "me/my-kernel",
+                "title": "My Kernel",
+                "code_file": "run.py",
+                "language": "python",
+                "kernel_type": "script"
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let push = |version: Option<u32>| {
+            Mock::given(method("POST"))
+                .and(path("/api/v1/kernels/push"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "ref": "/code/me/my-kernel",
+                    "url": "https://www.kaggle.com/code/me/my-kernel",
+                    "versionNumber": version,
+                    "error": null
+                })))
+        };
+        let interval = Duration::from_millis(1);
+
+        push(Some(3)).mount(&server).await;
+        mock_statuses(
+            &server,
+            "/api/v1/kernels/status",
+            &[
+                serde_json::json!({"status": "complete", "versionNumber": 2}),
+                serde_json::json!({"status": "running", "versionNumber": 3}),
+                serde_json::json!({"status": "complete", "versionNumber": 3}),
+            ],
+        )
+        .await;
+        let (resp, outputs) = kaggle
+            .push_and_wait(dir.path(), Duration::from_secs(10), interval, false)
+            .await
+            .unwrap();
+        assert_eq!(resp.kernel().unwrap().to_string(), "me/my-kernel");
+        assert_eq!(resp.version_number(), Some(3));
+        assert!(outputs.is_empty());
+        assert_eq!(requests_to(&server, "/api/v1/kernels/status").await, 3);
+
+        // without versions the finished previous run is skipped until the new
+        // run was seen
+        server.reset().await;
+        push(None).mount(&server).await;
+        mock_statuses(
+            &server,
+            "/api/v1/kernels/status",
+            &[
+                serde_json::json!({"status": "complete"}),
+                serde_json::json!({"status": "complete"}),
+                serde_json::json!({"status": "queued"}),
+                serde_json::json!({"status": "complete"}),
+            ],
+        )
+        .await;
+        kaggle
+            .push_and_wait(dir.path(), Duration::from_secs(10), interval, false)
+            .await
+            .unwrap();
+        assert_eq!(requests_to(&server, "/api/v1/kernels/status").await, 4);
+    }
+
+    #[tokio::test]
+    async fn kernels_output_pages() {
+        let server = MockServer::start().await;
+        let kaggle = mock_kaggle(&server);
+        let dir = TempDir::new().unwrap();
+        let output = |files: serde_json::Value, next: &str| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "files": files,
+                "log": "[]",
+                "nextPageToken": next
+            }))
+        };
+        Mock::given(method("GET"))
+            .and(path("/api/v1/kernels/output"))
+            .and(query_param("pageToken", "page-2"))
+            .respond_with(output(
+                serde_json::json!([
+                    {"fileName": "plots/loss.csv", "url": {"content": format!("{}/files/loss.csv", server.uri())}}
+                ]),
+                "",
+            ))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/kernels/output"))
+            .respond_with(output(
+                serde_json::json!([
+                    {"fileName": "submission.csv", "url": {"content": format!("{}/files/submission.csv", server.uri())}},
+                    {"fileName": "notes.txt", "url": {"content": "inline notes"}}
+                ]),
+                "page-2",
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/files/submission.csv"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("id,label\n1,0\n"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/files/loss.csv"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("epoch,loss\n"))
+            .mount(&server)
+            .await;
+
+        let progress = Rc::new(std::cell::RefCell::new(HashMap::new()));
+        let seen = Rc::clone(&progress);
+        let mut files = kaggle
+            .kernels_output(
+                KernelOutputRequest::new("me/my-kernel")
+                    .output(dir.path())
+                    .progress(move |p| {
+                        seen.borrow_mut()
+                            .insert(p.file_name.to_string(), (p.downloaded, p.total));
+                    }),
+            )
+            .await
+            .unwrap();
+        files.sort();
+
+        let mut expected = vec![
+            dir.path().join("my-kernel.log"),
+            dir.path().join("notes.txt"),
+            dir.path().join("plots/loss.csv"),
+            dir.path().join("submission.csv"),
+        ];
+        expected.sort();
+        assert_eq!(files, expected);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("submission.csv")).unwrap(),
+            "id,label\n1,0\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("notes.txt")).unwrap(),
+            "inline notes"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("plots/loss.csv")).unwrap(),
+            "epoch,loss\n"
+        );
+        assert_eq!(progress.borrow()["submission.csv"], (13, Some(13)));
+        assert_eq!(progress.borrow()["notes.txt"], (12, Some(12)));
+        assert_eq!(progress.borrow()["plots/loss.csv"], (11, Some(11)));
+
+        let requests = server.received_requests().await.unwrap();
+        let downloads: Vec<_> = requests
+            .iter()
+            .filter(|req| req.url.path().starts_with("/files/"))
+            .collect();
+        assert_eq!(downloads.len(), 2);
+        assert!(downloads
+            .iter()
+            .all(|req| !req.headers.contains_key("authorization")));
+        assert_eq!(requests_to(&server, "/api/v1/kernels/output").await, 2);
+    }
+
+    #[tokio::test]
+    async fn failed_output_download_keeps_existing_file() {
+        let server = MockServer::start().await;
+        let kaggle = mock_kaggle(&server);
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("submission.csv"), "old").unwrap();
+        Mock::given(method("GET"))
+            .and(path("/api/v1/kernels/output"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "files": [
+                    {"fileName": "submission.csv", "url": {"content": format!("{}/files/submission.csv", server.uri())}}
+                ]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/files/submission.csv"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        kaggle
+            .kernels_output(KernelOutputRequest::new("me/my-kernel").output(dir.path()))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("submission.csv")).unwrap(),
+            "old"
+        );
+        assert!(!dir.path().join("submission.csv.part").exists());
+    }
+
+    #[tokio::test]
+    async fn retry_and_resume_uploads() {
+        let server = MockServer::start().await;
+        let dir = TempDir::new().unwrap();
+        for name in &["a.csv", "b.csv", "c.csv"] {
+            fs::write(dir.path().join(name), name).unwrap();
+            mock_file_upload(&server, name).await;
+        }
+        Mock::given(method("PUT"))
+            .and(path("/upload/a.csv"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        // b fails once with a server error and succeeds when retried
+        Mock::given(method("PUT"))
+            .and(path("/upload/b.csv"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/upload/b.csv"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        // c is rejected, which is not retried
+        Mock::given(method("PUT"))
+            .and(path("/upload/c.csv"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&server)
+            .await;
+
+        let kaggle = mock_kaggle(&server);
+        let options = UploadOptions::default()
+            .retries(3)
+            .retry_delay(Duration::from_millis(1));
+        let err = kaggle
+            .upload_files(dir.path(), &[], &options)
+            .await
+            .unwrap_err();
+        let report = match err.downcast::<KaggleError>().unwrap() {
+            KaggleError::PartialUpload { report } => report,
+            err => panic!("unexpected error {}", err),
+        };
+        let tokens: Vec<_> = report.uploaded.iter().map(|(_, f)| f.token()).collect();
+        assert_eq!(tokens, vec!["token-a.csv", "token-b.csv"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, dir.path().join("c.csv"));
+        assert_eq!(requests_to(&server, "/upload/b.csv").await, 2);
+        assert_eq!(requests_to(&server, "/upload/c.csv").await, 1);
+
+        // only the failed file is uploaded again
+        server.reset().await;
+        mock_file_upload(&server, "c.csv").await;
+        Mock::given(method("PUT"))
+            .and(path("/upload/c.csv"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        let uploads = kaggle
+            .upload_files(dir.path(), &[], &options.resume(report))
+            .await
+            .unwrap();
+        let tokens: Vec<_> = uploads.iter().map(|f| f.token()).collect();
+        assert_eq!(tokens, vec!["token-a.csv", "token-b.csv", "token-c.csv"]);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn archives_of_directories_with_the_same_name() {
+        let server = MockServer::start().await;
+        let dir = TempDir::new().unwrap();
+        for (file, content) in &[("a/data/x.csv", "from a"), ("b/data/x.csv", "from b")] {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        mock_file_upload(&server, "data.tar").await;
+        Mock::given(method("PUT"))
+            .and(path("/upload/data.tar"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let resources: Vec<Resource> = serde_json::from_value(serde_json::json!([
+            {"path": "a/data/"},
+            {"path": "b/data/"}
+        ]))
+        .unwrap();
+        let options = UploadOptions::default()
+            .mode(crate::upload::UploadMode::Resources)
+            .archive_mode(ArchiveMode::Tar)
+            .archive_options(
+                ArchiveOptions::default().compression(crate::archive::ArchiveCompression::Stored),
+            );
+        mock_kaggle(&server)
+            .upload_files(dir.path(), &resources, &options)
+            .await
+            .unwrap();
+
+        let mut bodies: Vec<_> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|req| req.method.as_str() == "PUT")
+            .map(|req| String::from_utf8_lossy(&req.body).into_owned())
+            .collect();
+        assert_eq!(bodies.len(), 2);
+        bodies.sort_by_key(|body| body.contains("from b"));
+        assert!(bodies[0].contains("from a") && !bodies[0].contains("from b"));
+        assert!(bodies[1].contains("from b") && !bodies[1].contains("from a"));
+    }
+
+    /// A GCS-style resumable upload session that fails the chunk starting at
+    /// `fail_at` once.
+    struct MockSession {
+        data: std::sync::Mutex<Vec<u8>>,
+        fail_at: std::sync::Mutex<Option<usize>>,
+    }
+
+    impl MockSession {
+        fn status(&self, total: usize) -> ResponseTemplate {
+            let committed = self.data.lock().unwrap().len();
+            if committed == total {
+                ResponseTemplate::new(200)
+            } else if committed == 0 {
+                ResponseTemplate::new(308)
+            } else {
+                ResponseTemplate::new(308)
+                    .insert_header("Range", format!("bytes=0-{}", committed - 1).as_str())
+            }
+        }
+    }
+
+    impl wiremock::Respond for MockSession {
+        fn respond(&self, req: &wiremock::Request) -> ResponseTemplate {
+            if req.headers.contains_key(header::AUTHORIZATION) {
+                return ResponseTemplate::new(400);
+            }
+            let range = req.headers[header::CONTENT_RANGE].to_str().unwrap();
+            let (range, total) = range
+                .strip_prefix("bytes ")
+                .unwrap()
+                .split_once('/')
+                .unwrap();
+            let total = total.parse().unwrap();
+            if range == "*" {
+                return self.status(total);
+            }
+            let start: usize = range.split('-').next().unwrap().parse().unwrap();
+            if *self.fail_at.lock().unwrap() == Some(start) {
+                *self.fail_at.lock().unwrap() = None;
+                return ResponseTemplate::new(503);
+            }
+            let mut data = self.data.lock().unwrap();
+            assert_eq!(start, data.len());
+            data.extend_from_slice(&req.body);
+            drop(data);
+            self.status(total)
+        }
+    }
+
+    #[tokio::test]
+    async fn resume_interrupted_session() {
+        let server = MockServer::start().await;
+        mock_file_upload(&server, "data.bin").await;
+        Mock::given(method("POST"))
+            .and(path("/upload/data.bin"))
+            .and(header("x-goog-resumable", "start"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("Location", "/session/data.bin?upload_id=1"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let chunk = ResumableOptions::CHUNK_ALIGNMENT;
+        Mock::given(method("PUT"))
+            .and(path("/session/data.bin"))
+            .respond_with(MockSession {
+                data: Default::default(),
+                fail_at: std::sync::Mutex::new(Some(chunk)),
+            })
+            .mount(&server)
+            .await;
+
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("data.bin");
+        let content: Vec<u8> = (0..chunk * 2 + 100).map(|i| i as u8).collect();
+        fs::write(&file, &content).unwrap();
+        let options = ResumableOptions::default()
+            .chunk_size(chunk)
+            .state_dir(dir.path().join("state"));
+        let state_file = UploadSession::state_file(&options.state_dir, &file).unwrap();
+        let kaggle = mock_kaggle(&server);
+        let upload = |kaggle: KaggleApiClient| {
+            let (file, options, state_file) = (file.clone(), options.clone(), state_file.clone());
+            let len = content.len() as u64;
+            async move {
+                kaggle
+                    .upload_resumable(
+                        &file,
+                        "data.bin",
+                        len,
+                        Duration::ZERO,
+                        &options,
+                        &state_file,
+                    )
+                    .await
+            }
+        };
+
+        // the second chunk is interrupted, the session is kept
+        assert!(upload(kaggle.clone()).await.is_err());
+        let session = UploadSession::load(&state_file).unwrap().unwrap();
+        assert_eq!(
+            session.session_url,
+            format!("{}/session/data.bin?upload_id=1", server.uri())
+        );
+
+        // a restarted upload queries the status and continues with the
+        // second chunk
+        assert_eq!(upload(kaggle).await.unwrap(), "token-data.bin");
+        assert!(UploadSession::load(&state_file).unwrap().is_none());
+
+        let puts: Vec<_> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|req| req.method.as_str() == "PUT")
+            .map(|req| {
+                req.headers[header::CONTENT_RANGE]
+                    .to_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        let total = content.len();
+        assert_eq!(
+            puts,
+            vec![
+                format!("bytes 0-{}/{}", chunk - 1, total),
+                format!("bytes {}-{}/{}", chunk, chunk * 2 - 1, total),
+                format!("bytes */{}", total),
+                format!("bytes {}-{}/{}", chunk, chunk * 2 - 1, total),
+                format!("bytes {}-{}/{}", chunk * 2, total - 1, total),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn version_plan_rejects_unplanned_files() {
+        let server = MockServer::start().await;
+        let dir = TempDir::new().unwrap();
+        let folder = dir.path();
+        fs::write(
+            folder.join("dataset-metadata.json"),
+            r#"{"id": "owner/data", "title": "Data", "licenses": [{"name": "CC0-1.0"}]}"#,
+        )
+        .unwrap();
+        fs::write(folder.join("a.csv"), "a").unwrap();
+        fs::write(folder.join("b.tmp"), "b").unwrap();
+
+        let options = UploadOptions::default().ignore_pattern("*.tmp");
+        let meta = KaggleApiClient::read_dataset_metadata_file(folder)
+            .await
+            .unwrap();
+        let local = KaggleApiClient::planned_files(folder, &meta, &options, false)
+            .await
+            .unwrap();
+        assert_eq!(local.len(), 1);
+        assert_eq!(local[0].sha256, None);
+        let plan = DatasetVersionPlan::new("owner/data", folder, local, &[], None)
+            .with_upload_options(options);
+
+        let kaggle = mock_kaggle(&server);
+        fs::write(folder.join("new.csv"), "new").unwrap();
+        let err = kaggle
+            .dataset_create_version_from_plan(&plan, "notes", false, false)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("not planned: [new.csv]"),
+            "{}",
+            err
+        );
+
+        // a deserialized plan uses the default options, which don't ignore
+        // the `.tmp` file
+        fs::remove_file(folder.join("new.csv")).unwrap();
+        let plan: DatasetVersionPlan =
+            serde_json::from_value(serde_json::to_value(&plan).unwrap()).unwrap();
+        let err = kaggle
+            .dataset_create_version_from_plan(&plan, "notes", false, false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not planned: [b.tmp]"), "{}", err);
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn version_plan_lists_all_pages() {
+        let server = MockServer::start().await;
+        let dir = TempDir::new().unwrap();
+        let folder = dir.path();
+        fs::write(
+            folder.join("dataset-metadata.json"),
+            r#"{"id": "owner/data", "title": "Data", "licenses": [{"name": "CC0-1.0"}]}"#,
+        )
+        .unwrap();
+        fs::write(folder.join("a.csv"), "a").unwrap();
+        fs::create_dir_all(folder.join("images")).unwrap();
+        fs::write(folder.join("images/cat.jpg"), "cat").unwrap();
+
+        let file = |name: &str, bytes: u64| {
+            serde_json::json!({
+                "ref": name,
+                "creationDate": "2020-01-01T00:00:00.000Z",
+                "datasetRef": "owner/data",
+                "description": null,
+                "fileType": ".csv",
+                "name": name,
+                "ownerRef": "owner",
+                "totalBytes": bytes,
+                "url": "",
+                "columns": []
+            })
+        };
+        Mock::given(method("GET"))
+            .and(path("/api/v1/datasets/list/owner/data"))
+            .and(query_param("pageToken", "page-2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "errorMessage": null,
+                "datasetFiles": [file("images/cat.jpg", 3), file("old.csv", 2)],
+                "nextPageToken": ""
+            })))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/datasets/list/owner/data"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "errorMessage": null,
+                "datasetFiles": [file("a.csv", 1)],
+                "nextPageToken": "page-2"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/datasets/metadata/owner/data"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "info": {
+                    "datasetId": 1,
+                    "datasetSlug": "data",
+                    "ownerUser": "owner",
+                    "usabilityRating": 1.0,
+                    "totalViews": 0,
+                    "totalVotes": 0,
+                    "totalDownloads": 0,
+                    "title": "Data",
+                    "subtitle": "",
+                    "description": "",
+                    "isPrivate": false,
+                    "licenses": [{"name": "CC0-1.0"}],
+                    "keywords": [],
+                    "collaborators": [],
+                    "data": []
+                }
+            })))
+            .mount(&server)
+            .await;
+
+        let plan = mock_kaggle(&server)
+            .dataset_version_plan_with(
+                folder,
+                UploadOptions::default().archive_mode(ArchiveMode::Zip),
+                None,
+            )
+            .await
+            .unwrap();
+        let names =
+            |files: &[PlannedFile]| files.iter().map(|f| f.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&plan.unchanged), vec!["a.csv", "images.zip"]);
+        assert!(plan.added.is_empty());
+        assert!(plan.changed.is_empty());
+        assert_eq!(names(&plan.removed), vec!["old.csv"]);
+        assert_eq!(
+            requests_to(&server, "/api/v1/datasets/list/owner/data").await,
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn metadata_download_keeps_the_dataset_id() {
+        let server = MockServer::start().await;
+        let dir = TempDir::new().unwrap();
+        Mock::given(method("GET"))
+            .and(path("/api/v1/datasets/metadata/org/data"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "info": {
+                    "datasetId": 1,
+                    "datasetSlug": "data",
+                    "ownerUser": null,
+                    "usabilityRating": 1.0,
+                    "totalViews": 0,
+                    "totalVotes": 0,
+                    "totalDownloads": 0,
+                    "title": "Data",
+                    "subtitle": "",
+                    "description": "",
+                    "isPrivate": false,
+                    "licenses": [{"name": "CC0-1.0"}],
+                    "keywords": [],
+                    "collaborators": [],
+                    "data": []
+                }
+            })))
+            .mount(&server)
+            .await;
+
+        let file = mock_kaggle(&server)
+            .dataset_metadata_download("org/data", Some(dir.path().to_path_buf()))
+            .await
+            .unwrap();
+        let metadata = KaggleApiClient::read_dataset_metadata_file(dir.path())
+            .await
+            .unwrap();
+        assert_eq!(file, dir.path().join("dataset-metadata.json"));
+        assert_eq!(metadata.id, "org/data");
+    }
+
+    #[test]
+    fn retryable_errors() {
+        let api = |err| anyhow::Error::from(KaggleError::Api { err });
+        assert!(is_retryable(&api(ApiError::Other(503))));
+        assert!(is_retryable(&api(ApiError::Other(429))));
+        assert!(!is_retryable(&api(ApiError::Other(404))));
+        assert!(!is_retryable(&api(ApiError::Unauthorized)));
+        assert!(!is_retryable(&anyhow::Error::from(
+            KaggleError::FileNotFound(PathBuf::from("data.csv"))
+        )));
+    }
+
+    #[tokio::test]
+    async fn skip_unchanged_archives() {
+        let server = MockServer::start().await;
+        let dir = TempDir::new().unwrap();
+        let folder = dir.path().join("dataset");
+        fs::create_dir_all(folder.join("images")).unwrap();
+        fs::write(folder.join("images/cat.jpg"), "cat").unwrap();
+
+        let archive_options = ArchiveOptions::default().deterministic(true);
+        let previous = ArchiveMode::Zip
+            .make_archive_with(
+                folder.join("images"),
+                dir.path().join("images"),
+                &archive_options,
+            )
+            .unwrap()
+            .unwrap();
+        let digest = crate::archive::sha256_digest(previous).unwrap();
+
+        let options = UploadOptions::default()
+            .archive_mode(ArchiveMode::Zip)
+            .archive_options(archive_options)
+            .retries(0)
+            .known_archive(&digest, DatasetUploadFile::new("previous-token"));
+        let uploads = mock_kaggle(&server)
+            .upload_files(&folder, &[], &options)
+            .await
+            .unwrap();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].token(), "previous-token");
+        assert!(server.received_requests().await.unwrap().is_empty());
+
+        // the known upload keeps its token but takes the current description
+        let resources = [Resource {
+            path: "images/".to_string(),
+            description: Some("Pictures of pets".to_string()),
+            schema: None,
+        }];
+        let uploads = mock_kaggle(&server)
+            .upload_files(&folder, &resources, &options)
+            .await
+            .unwrap();
+        assert_eq!(uploads[0].token(), "previous-token");
+        assert_eq!(
+            uploads[0].description().map(String::as_str),
+            Some("Pictures of pets")
+        );
+        assert!(server.received_requests().await.unwrap().is_empty());
+
+        // a changed directory is uploaded again
+        fs::write(folder.join("images/dog.jpg"), "dog").unwrap();
+        assert!(mock_kaggle(&server)
+            .upload_files(&folder, &[], &options)
+            .await
+            .is_err());
+        assert!(!server.received_requests().await.unwrap().is_empty());
+    }
+
     #[test]
     fn competition_query() {
         let kaggle = kaggle();
@@ -1,6 +1,8 @@
-use crate::models::Error;
+use crate::models::{Error, ValidationReport};
+use crate::upload::UploadReport;
 use std::fmt;
 use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Describes API errors
@@ -34,6 +36,27 @@ pub enum KaggleError {
         #[from]
         err: ApiError,
     },
+    #[error(
+        "Failed to upload {} of {} files",
+        report.failed.len(),
+        report.failed.len() + report.uploaded.len()
+    )]
+    PartialUpload { report: UploadReport },
+    #[error("Invalid metadata:\n{}", report)]
+    Validation { report: ValidationReport },
+    #[error("Kaggle rejected the dataset: {}", msg)]
+    DatasetRejected {
+        msg: String,
+        invalid_tags: Vec<String>,
+    },
+    #[error("Processing of dataset {} failed: {}", dataset, msg)]
+    DatasetFailed { dataset: String, msg: String },
+    #[error("Invalid notebook: {}", msg)]
+    Notebook { msg: String },
+    #[error("Run of kernel {} failed: {}", kernel, msg)]
+    KernelFailed { kernel: String, msg: String },
+    #[error("Timed out after {:?} waiting for {}", timeout, name)]
+    Timeout { name: String, timeout: Duration },
 }
 
 impl KaggleError {
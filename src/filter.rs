@@ -0,0 +1,154 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::error::KaggleError;
+
+/// Decides which files of a dataset folder are skipped when uploading and
+/// archiving.
+///
+/// Patterns use the gitignore syntax. They are read from the
+/// [`IgnoreFilter::FILE_NAME`] file in the root of the folder and can be
+/// extended with additional patterns. A set of default patterns always skips
+/// the metadata files, version control directories and common editor and
+/// notebook artifacts, these can be re-included with a negated pattern like
+/// `!.ipynb_checkpoints/`.
+#[derive(Debug, Clone)]
+pub struct IgnoreFilter {
+    root: PathBuf,
+    matcher: Gitignore,
+}
+
+impl IgnoreFilter {
+    /// The name of the ignore file in the root of a dataset folder.
+    pub const FILE_NAME: &'static str = ".kaggleignore";
+
+    /// Patterns that are always applied before the patterns of the
+    /// [`IgnoreFilter::FILE_NAME`] file.
+    pub const DEFAULT_PATTERNS: &'static [&'static str] = &[
+        "/dataset-metadata.json",
+        "/datapackage.json",
+        "/kernel-metadata.json",
+        "/.kaggleignore",
+        ".git/",
+        ".hg/",
+        ".svn/",
+        ".DS_Store",
+        "Thumbs.db",
+        "__pycache__/",
+        ".ipynb_checkpoints/",
+    ];
+
+    /// Create a new filter for the folder at `root`.
+    ///
+    /// The `.kaggleignore` file in `root` is read if it exists, the `patterns`
+    /// are applied last and take precedence over the file's patterns.
+    pub fn new<S: AsRef<str>>(root: impl AsRef<Path>, patterns: &[S]) -> anyhow::Result<Self> {
+        let root = root.as_ref();
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in Self::DEFAULT_PATTERNS {
+            builder.add_line(None, pattern)?;
+        }
+        let ignore_file = root.join(Self::FILE_NAME);
+        if ignore_file.is_file() {
+            if let Some(err) = builder.add(&ignore_file) {
+                return Err(KaggleError::meta(format!(
+                    "Invalid ignore file {}: {}",
+                    ignore_file.display(),
+                    err
+                ))
+                .into());
+            }
+        }
+        for pattern in patterns {
+            builder
+                .add_line(None, pattern.as_ref())
+                .map_err(|err| KaggleError::meta(format!("Invalid ignore pattern: {}", err)))?;
+        }
+        Ok(Self {
+            root: root.to_path_buf(),
+            matcher: builder.build()?,
+        })
+    }
+
+    /// The folder the patterns are relative to.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Whether the path, or any of its parent directories, is ignored.
+    ///
+    /// The `path` is either relative to the root or an absolute path inside the
+    /// root. Paths outside of the root are never ignored.
+    pub fn is_ignored(&self, path: impl AsRef<Path>, is_dir: bool) -> bool {
+        let path = path.as_ref();
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        if relative.has_root() || relative.as_os_str().is_empty() {
+            return false;
+        }
+        self.matcher
+            .matched_path_or_any_parents(relative, is_dir)
+            .is_ignore()
+    }
+
+    /// All files in the folder that are not ignored, relative to the root and
+    /// sorted by path.
+    pub fn files(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let walker = WalkDir::new(&self.root)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| !self.is_ignored(e.path(), e.file_type().is_dir()));
+        for entry in walker {
+            let entry = entry?;
+            if entry.path().is_file() {
+                files.push(entry.path().strip_prefix(&self.root)?.to_path_buf());
+            }
+        }
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn ignore_defaults_and_patterns() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        for file in &[
+            "dataset-metadata.json",
+            "data.csv",
+            "notes.tmp",
+            ".DS_Store",
+            ".git/config",
+            "images/cat.jpg",
+            "images/.ipynb_checkpoints/cat-checkpoint.jpg",
+            "images/__pycache__/mod.pyc",
+            "scratch/draft.txt",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        fs::write(root.join(".kaggleignore"), "*.tmp\nscratch/\n").unwrap();
+
+        let filter = IgnoreFilter::new(root, &["!.DS_Store"]).unwrap();
+        assert!(filter.is_ignored(root.join("notes.tmp"), false));
+        assert!(filter.is_ignored(".git/config", false));
+        assert!(!filter.is_ignored("data.csv", false));
+
+        assert_eq!(
+            filter.files().unwrap(),
+            vec![
+                PathBuf::from(".DS_Store"),
+                PathBuf::from("data.csv"),
+                PathBuf::from("images/cat.jpg"),
+            ]
+        );
+    }
+}
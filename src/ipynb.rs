@@ -0,0 +1,531 @@
+//! Jupyter notebooks in the nbformat v4 format.
+//!
+//! Notebooks are cleaned before they are pushed and after they are pulled,
+//! so they stay diff-friendly under version control, and can be converted
+//! from and to scripts in the percent format:
+//!
+//! ```text
+//! # %% [markdown]
+//! # # Title
+//!
+//! # %%
+//! print("hello")
+//! ```
+
+use serde::de::Deserializer;
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::KaggleError;
+
+/// A notebook in the nbformat v4 format.
+///
+/// Example
+///
+/// ```
+/// use kaggle::ipynb::{Notebook, StripOptions};
+///
+/// let mut notebook = Notebook::from_percent_script("# %%\nprint('hello')\n");
+/// notebook.strip(&StripOptions::default());
+/// assert_eq!(notebook.to_percent_script(), "# %%\nprint('hello')\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notebook {
+    pub cells: Vec<Cell>,
+    #[serde(default)]
+    pub metadata: Map<String, Value>,
+    pub nbformat: u32,
+    pub nbformat_minor: u32,
+}
+
+/// A cell of a notebook.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cell_type", rename_all = "lowercase")]
+pub enum Cell {
+    Code(CodeCell),
+    Markdown(TextCell),
+    Raw(TextCell),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CodeCell {
+    #[serde(default)]
+    pub execution_count: Option<u64>,
+    /// Required since nbformat 4.5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub metadata: Map<String, Value>,
+    #[serde(default)]
+    pub outputs: Vec<Output>,
+    pub source: CellSource,
+}
+
+/// A markdown or raw cell.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextCell {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachments: Option<Value>,
+    /// Required since nbformat 4.5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub metadata: Map<String, Value>,
+    pub source: CellSource,
+}
+
+/// An output of a code cell.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "output_type", rename_all = "snake_case")]
+pub enum Output {
+    Stream {
+        name: String,
+        text: CellSource,
+    },
+    DisplayData {
+        data: Map<String, Value>,
+        #[serde(default)]
+        metadata: Map<String, Value>,
+    },
+    ExecuteResult {
+        execution_count: Option<u64>,
+        data: Map<String, Value>,
+        #[serde(default)]
+        metadata: Map<String, Value>,
+    },
+    Error {
+        ename: String,
+        evalue: String,
+        traceback: Vec<String>,
+    },
+}
+
+/// The multiline text of a cell.
+///
+/// nbformat allows both a single string and a list of lines, the source is
+/// always written as a list of lines that keep their line endings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CellSource(pub String);
+
+impl CellSource {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The lines as they are stored in the notebook file.
+    pub fn lines(&self) -> Vec<&str> {
+        self.0.split_inclusive('\n').collect()
+    }
+}
+
+impl<T: Into<String>> From<T> for CellSource {
+    fn from(source: T) -> Self {
+        CellSource(source.into())
+    }
+}
+
+impl Serialize for CellSource {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.lines().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CellSource {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Text {
+            Single(String),
+            Lines(Vec<String>),
+        }
+        Ok(match Text::deserialize(deserializer)? {
+            Text::Single(text) => CellSource(text),
+            Text::Lines(lines) => CellSource(lines.concat()),
+        })
+    }
+}
+
+impl Cell {
+    pub fn code(source: impl Into<CellSource>) -> Self {
+        Cell::Code(CodeCell {
+            source: source.into(),
+            ..Default::default()
+        })
+    }
+
+    pub fn markdown(source: impl Into<CellSource>) -> Self {
+        Cell::Markdown(TextCell {
+            source: source.into(),
+            ..Default::default()
+        })
+    }
+
+    pub fn raw(source: impl Into<CellSource>) -> Self {
+        Cell::Raw(TextCell {
+            source: source.into(),
+            ..Default::default()
+        })
+    }
+
+    pub fn source(&self) -> &CellSource {
+        match self {
+            Cell::Code(cell) => &cell.source,
+            Cell::Markdown(cell) | Cell::Raw(cell) => &cell.source,
+        }
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut Map<String, Value> {
+        match self {
+            Cell::Code(cell) => &mut cell.metadata,
+            Cell::Markdown(cell) | Cell::Raw(cell) => &mut cell.metadata,
+        }
+    }
+}
+
+/// What is removed from a notebook by [`Notebook::strip`].
+#[derive(Debug, Clone)]
+pub struct StripOptions {
+    /// Remove the outputs of code cells
+    pub outputs: bool,
+    /// Reset the execution counts of code cells
+    pub execution_counts: bool,
+    /// Keys removed from the metadata of every cell
+    pub cell_metadata: Vec<String>,
+    /// Keys removed from the metadata of the notebook
+    pub notebook_metadata: Vec<String>,
+}
+
+impl Default for StripOptions {
+    fn default() -> Self {
+        Self {
+            outputs: true,
+            execution_counts: true,
+            cell_metadata: ["collapsed", "execution", "papermill", "scrolled", "trusted"]
+                .iter()
+                .map(|key| key.to_string())
+                .collect(),
+            notebook_metadata: ["papermill", "widgets"]
+                .iter()
+                .map(|key| key.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl StripOptions {
+    pub fn outputs(mut self, outputs: bool) -> Self {
+        self.outputs = outputs;
+        self
+    }
+
+    pub fn execution_counts(mut self, execution_counts: bool) -> Self {
+        self.execution_counts = execution_counts;
+        self
+    }
+
+    pub fn cell_metadata(mut self, cell_metadata: Vec<String>) -> Self {
+        self.cell_metadata = cell_metadata;
+        self
+    }
+
+    pub fn notebook_metadata(mut self, notebook_metadata: Vec<String>) -> Self {
+        self.notebook_metadata = notebook_metadata;
+        self
+    }
+}
+
+impl Notebook {
+    /// The only supported major version of nbformat.
+    pub const NBFORMAT: u32 = 4;
+
+    /// The file extensions of Python, R and Julia notebooks.
+    pub const EXTENSIONS: &'static [&'static str] = &["ipynb", "irnb", "ijlnb"];
+
+    /// A new nbformat 4.4 notebook, which does not require cell ids.
+    pub fn new(cells: Vec<Cell>) -> Self {
+        Self {
+            cells,
+            metadata: Map::new(),
+            nbformat: Self::NBFORMAT,
+            nbformat_minor: 4,
+        }
+    }
+
+    /// Whether the file at `path` is a notebook judging by its extension.
+    pub fn is_notebook_file(path: impl AsRef<Path>) -> bool {
+        path.as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| Self::EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+    }
+
+    /// Check that the notebook uses nbformat v4.
+    pub fn validate(&self) -> Result<(), KaggleError> {
+        if self.nbformat == Self::NBFORMAT {
+            Ok(())
+        } else {
+            Err(invalid(format!(
+                "Unsupported nbformat version {}.{}, expected {}",
+                self.nbformat,
+                self.nbformat_minor,
+                Self::NBFORMAT
+            )))
+        }
+    }
+
+    /// Remove outputs, execution counts and metadata as configured by the
+    /// `options`.
+    pub fn strip(&mut self, options: &StripOptions) {
+        for key in &options.notebook_metadata {
+            self.metadata.shift_remove(key);
+        }
+        for cell in &mut self.cells {
+            if let Cell::Code(code) = cell {
+                if options.outputs {
+                    code.outputs.clear();
+                }
+                if options.execution_counts {
+                    code.execution_count = None;
+                }
+            }
+            let metadata = cell.metadata_mut();
+            for key in &options.cell_metadata {
+                metadata.shift_remove(key);
+            }
+        }
+    }
+
+    /// Serialize the notebook like Jupyter does, indented by one space and
+    /// with a trailing newline.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        let mut buf = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(b" ");
+        let mut ser = serde_json::Serializer::with_formatter(&mut buf, formatter);
+        self.serialize(&mut ser)?;
+        buf.push(b'\n');
+        Ok(String::from_utf8(buf).expect("serde_json writes valid utf-8"))
+    }
+
+    /// Parse a script in the percent format.
+    ///
+    /// Cells start with a `# %%` line, `# %% [markdown]` and `# %% [raw]`
+    /// start markdown and raw cells whose lines are commented out. Code
+    /// before the first marker becomes the first cell.
+    pub fn from_percent_script(script: &str) -> Self {
+        let mut cells = Vec::new();
+        let mut kind = None;
+        let mut lines = Vec::new();
+        for line in script.lines() {
+            let marker = line
+                .strip_prefix("# %%")
+                .or_else(|| line.strip_prefix("#%%"));
+            if let Some(marker) = marker {
+                cells.extend(percent_cell(kind, &lines));
+                let marker = marker.trim_start();
+                kind = Some(
+                    if marker.starts_with("[markdown]") || marker.starts_with("[md]") {
+                        CellKind::Markdown
+                    } else if marker.starts_with("[raw]") {
+                        CellKind::Raw
+                    } else {
+                        CellKind::Code
+                    },
+                );
+                lines.clear();
+            } else {
+                lines.push(line);
+            }
+        }
+        cells.extend(percent_cell(kind, &lines));
+        Self::new(cells)
+    }
+
+    /// Render the notebook as a script in the percent format, see
+    /// [`Notebook::from_percent_script`].
+    pub fn to_percent_script(&self) -> String {
+        let mut script = String::new();
+        for (idx, cell) in self.cells.iter().enumerate() {
+            if idx > 0 {
+                script.push('\n');
+            }
+            let source = cell.source().as_str();
+            match cell {
+                Cell::Code(_) => {
+                    script.push_str("# %%\n");
+                    script.push_str(source);
+                }
+                Cell::Markdown(_) | Cell::Raw(_) => {
+                    script.push_str(if let Cell::Markdown(_) = cell {
+                        "# %% [markdown]\n"
+                    } else {
+                        "# %% [raw]\n"
+                    });
+                    for line in source.lines() {
+                        if line.is_empty() {
+                            script.push_str("#\n");
+                        } else {
+                            script.push_str("# ");
+                            script.push_str(line);
+                            script.push('\n');
+                        }
+                    }
+                }
+            }
+            if !script.ends_with('\n') {
+                script.push('\n');
+            }
+        }
+        script
+    }
+}
+
+impl FromStr for Notebook {
+    type Err = KaggleError;
+
+    /// Parse a notebook file, failing for other versions than nbformat v4.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value: Value = serde_json::from_str(s).map_err(invalid)?;
+        match value.get("nbformat").and_then(Value::as_u64) {
+            Some(version) if version == u64::from(Self::NBFORMAT) => {}
+            Some(version) => {
+                return Err(invalid(format!(
+                    "Unsupported nbformat version {}, expected {}",
+                    version,
+                    Self::NBFORMAT
+                )))
+            }
+            None => return Err(invalid("Missing nbformat version")),
+        }
+        serde_json::from_value(value).map_err(invalid)
+    }
+}
+
+fn invalid(msg: impl fmt::Display) -> KaggleError {
+    KaggleError::Notebook {
+        msg: msg.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CellKind {
+    Code,
+    Markdown,
+    Raw,
+}
+
+/// Turn the lines following a percent marker into a cell, without leading
+/// and trailing blank lines. Blank code before the first marker is skipped.
+fn percent_cell(kind: Option<CellKind>, lines: &[&str]) -> Option<Cell> {
+    let start = lines.iter().position(|l| !l.trim().is_empty());
+    let end = lines.iter().rposition(|l| !l.trim().is_empty());
+    let lines = match (start, end) {
+        (Some(start), Some(end)) => &lines[start..=end],
+        _ => &[][..],
+    };
+    match kind {
+        None if lines.is_empty() => None,
+        None | Some(CellKind::Code) => Some(Cell::code(lines.join("\n"))),
+        Some(kind) => {
+            let text = lines
+                .iter()
+                .map(|line| {
+                    line.strip_prefix("# ")
+                        .or_else(|| line.strip_prefix('#'))
+                        .unwrap_or(line)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            Some(if let CellKind::Raw = kind {
+                Cell::raw(text)
+            } else {
+                Cell::markdown(text)
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTEBOOK: &str = r##"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "metadata": {},
+   "source": "# Title\n\nSome text"
+  },
+  {
+   "cell_type": "code",
+   "execution_count": 3,
+   "metadata": {"trusted": true, "tags": ["setup"]},
+   "outputs": [{"output_type": "stream", "name": "stdout", "text": ["1\n"]}],
+   "source": ["x = 1\n", "print(x)"]
+  }
+ ],
+ "metadata": {"kernelspec": {"name": "python3"}, "widgets": {}},
+ "nbformat": 4,
+ "nbformat_minor": 4
+}"##;
+
+    #[test]
+    fn parse_and_strip() {
+        let mut notebook: Notebook = NOTEBOOK.parse().unwrap();
+        assert_eq!(notebook.cells.len(), 2);
+        assert_eq!(notebook.cells[1].source().as_str(), "x = 1\nprint(x)");
+
+        notebook.strip(&StripOptions::default());
+        let json: Value = serde_json::from_str(&notebook.to_json().unwrap()).unwrap();
+        assert_eq!(
+            json["metadata"],
+            serde_json::json!({"kernelspec": {"name": "python3"}})
+        );
+        assert_eq!(
+            json["cells"][0]["source"],
+            serde_json::json!(["# Title\n", "\n", "Some text"])
+        );
+        assert_eq!(
+            json["cells"][1],
+            serde_json::json!({
+                "cell_type": "code",
+                "execution_count": null,
+                "metadata": {"tags": ["setup"]},
+                "outputs": [],
+                "source": ["x = 1\n", "print(x)"]
+            })
+        );
+
+        let v3 = r#"{"worksheets": [], "metadata": {}, "nbformat": 3, "nbformat_minor": 0}"#;
+        assert!(v3.parse::<Notebook>().is_err());
+    }
+
+    #[test]
+    fn percent_script_round_trip() {
+        let notebook: Notebook = NOTEBOOK.parse().unwrap();
+        let script = notebook.to_percent_script();
+        assert_eq!(
+            script,
+            "# %% [markdown]\n# # Title\n#\n# Some text\n\n# %%\nx = 1\nprint(x)\n"
+        );
+
+        let parsed = Notebook::from_percent_script(&script);
+        assert_eq!(
+            parsed.cells,
+            vec![
+                Cell::markdown("# Title\n\nSome text"),
+                Cell::code("x = 1\nprint(x)")
+            ]
+        );
+        assert_eq!(parsed.to_percent_script(), script);
+
+        let preamble = Notebook::from_percent_script("import os\n\n# %% [raw]\n# raw\n");
+        assert_eq!(
+            preamble.cells,
+            vec![Cell::code("import os"), Cell::raw("raw")]
+        );
+    }
+}
@@ -35,12 +35,17 @@
 pub mod archive;
 pub mod client;
 mod error;
+pub mod filter;
+pub mod ipynb;
 pub mod models;
 mod none_as_empty;
 pub mod query;
 pub mod request;
+pub mod schema;
+pub mod upload;
 
 pub use client::{Authentication, KaggleApiClient, KaggleApiClientBuilder};
+pub use error::{ApiError, KaggleError};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,398 @@
+//! Models of the [Frictionless Data Package] and [Table Schema] specs, used by
+//! the legacy `datapackage.json` metadata file.
+//!
+//! Every model keeps unknown properties in its `extra` map, so a package
+//! round-trips without losing information.
+//!
+//! [Frictionless Data Package]: https://specs.frictionlessdata.io/data-package/
+//! [Table Schema]: https://specs.frictionlessdata.io/table-schema/
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::str::FromStr;
+
+use crate::models::metadata::{DatasetMetadataFile, Field, Resource, Schema};
+use crate::models::License;
+
+/// A Data Package descriptor.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DataPackage {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    /// The `owner/slug` identifier of the dataset
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub homepage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub keywords: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub licenses: Vec<PackageLicense>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub sources: Vec<PackageSource>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub contributors: Vec<Contributor>,
+    #[serde(default)]
+    pub resources: Vec<DataResource>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A license of a package or resource.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PackageLicense {
+    /// An Open Definition license id like `CC0-1.0`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub title: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A raw source of a package or resource.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PackageSource {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub email: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A person or organization that contributed to a package.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Contributor {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub organization: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A single value or a list of values, like the `path` of a resource or the
+/// `primaryKey` of a schema.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        match self {
+            OneOrMany::One(value) => std::slice::from_ref(value).iter(),
+            OneOrMany::Many(values) => values.iter(),
+        }
+    }
+}
+
+/// A Data Resource descriptor.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DataResource {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    /// One path, or multiple paths whose files are concatenated
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub path: Option<OneOrMany<String>>,
+    /// Inline data instead of a path
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub data: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub mediatype: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub encoding: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub bytes: Option<u64>,
+    /// The hash of the file, `md5` unless prefixed with the algorithm like
+    /// `sha256:...`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub schema: Option<TableSchema>,
+    /// A CSV dialect descriptor
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub dialect: Option<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub sources: Vec<PackageSource>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub licenses: Vec<PackageLicense>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl DataResource {
+    /// All paths of the resource.
+    pub fn paths(&self) -> impl Iterator<Item = &String> {
+        self.path.iter().flat_map(OneOrMany::iter)
+    }
+
+    /// The metadata resources of the upload, one per path.
+    ///
+    /// Uploaded files only carry a description and columns, these are
+    /// derived from the resource's description and schema when uploading.
+    /// The remaining properties like `format`, `bytes`, `hash` and field
+    /// constraints have no counterpart in the Kaggle api.
+    pub fn resources(&self) -> Vec<Resource> {
+        let schema = self.schema.as_ref().map(Schema::from);
+        self.paths()
+            .map(|path| Resource {
+                path: path.clone(),
+                description: self.description.clone(),
+                schema: schema.clone(),
+            })
+            .collect()
+    }
+}
+
+/// A Table Schema descriptor.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableSchema {
+    #[serde(default)]
+    pub fields: Vec<TableField>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub primary_key: Option<OneOrMany<String>>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub foreign_keys: Vec<ForeignKey>,
+    /// Values that are treated as missing, `[""]` if not set
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub missing_values: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A field of a [`TableSchema`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableField {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none", default)]
+    pub type_field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub constraints: Option<Constraints>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub rdf_type: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Constraints on the values of a [`TableField`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Constraints {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub required: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub unique: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub min_length: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_length: Option<u64>,
+    /// The minimum, a number or a value in the field's format
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub minimum: Option<Value>,
+    /// The maximum, a number or a value in the field's format
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub maximum: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pattern: Option<String>,
+    #[serde(rename = "enum", skip_serializing_if = "Option::is_none", default)]
+    pub enum_values: Option<Vec<Value>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A reference from fields of a schema to fields of another resource.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForeignKey {
+    pub fields: OneOrMany<String>,
+    pub reference: ForeignKeyReference,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The target of a [`ForeignKey`], an empty `resource` refers to the same
+/// resource.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForeignKeyReference {
+    pub resource: String,
+    pub fields: OneOrMany<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl From<&TableSchema> for Schema {
+    fn from(schema: &TableSchema) -> Self {
+        Schema {
+            fields: schema
+                .fields
+                .iter()
+                .map(|f| Field {
+                    name: f.name.clone(),
+                    description: f.description.clone().or_else(|| f.title.clone()),
+                    type_field: f.type_field.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<DataPackage> for DatasetMetadataFile {
+    fn from(package: DataPackage) -> Self {
+        let resources = package
+            .resources
+            .iter()
+            .flat_map(DataResource::resources)
+            .collect();
+        let subtitle = package
+            .extra
+            .get("subtitle")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        DatasetMetadataFile {
+            title: package.title.unwrap_or_default(),
+            id: package.id.or(package.name).unwrap_or_default(),
+            subtitle,
+            description: package.description,
+            id_no: None,
+            is_private: None,
+            collaborators: Vec::new(),
+            licenses: package
+                .licenses
+                .iter()
+                .filter_map(|l| l.name.as_deref())
+                .filter_map(|name| License::from_str(name).ok())
+                .collect(),
+            resources,
+            keywords: package.keywords,
+            data: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn package() -> Value {
+        json!({
+            "id": "me/weather",
+            "title": "Weather stations",
+            "profile": "tabular-data-package",
+            "licenses": [{"name": "CC0-1.0", "title": "CC0 1.0"}],
+            "keywords": ["weather"],
+            "contributors": [{"title": "Me", "role": "author"}],
+            "x-custom": {"kept": true},
+            "resources": [
+                {
+                    "name": "readings",
+                    "path": ["readings-2019.csv", "readings-2020.csv"],
+                    "description": "Hourly readings",
+                    "format": "csv",
+                    "mediatype": "text/csv",
+                    "bytes": 1024,
+                    "hash": "sha256:abc",
+                    "dialect": {"delimiter": ";"},
+                    "schema": {
+                        "fields": [
+                            {
+                                "name": "station",
+                                "type": "string",
+                                "constraints": {"required": true, "pattern": "[A-Z]+"}
+                            },
+                            {
+                                "name": "temperature",
+                                "type": "number",
+                                "description": "Degrees Celsius",
+                                "constraints": {"minimum": -90, "maximum": 60}
+                            },
+                            {"name": "kind", "type": "string", "constraints": {"enum": ["a", "b"]}}
+                        ],
+                        "primaryKey": ["station", "temperature"],
+                        "foreignKeys": [
+                            {"fields": "station", "reference": {"resource": "stations", "fields": "id"}}
+                        ],
+                        "missingValues": ["", "NA"]
+                    }
+                },
+                {"name": "stations", "path": "stations.csv"}
+            ]
+        })
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = package();
+        let parsed: DataPackage = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(parsed.resources[0].paths().count(), 2);
+        assert_eq!(
+            parsed.resources[0].schema.as_ref().unwrap().missing_values,
+            Some(vec!["".to_string(), "NA".to_string()])
+        );
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), value);
+    }
+
+    #[test]
+    fn into_metadata() {
+        let parsed: DataPackage = serde_json::from_value(package()).unwrap();
+        let metadata = DatasetMetadataFile::from(parsed);
+        assert_eq!(metadata.id, "me/weather");
+        assert_eq!(metadata.licenses, vec![License::Cc010]);
+        let paths: Vec<_> = metadata.resources.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["readings-2019.csv", "readings-2020.csv", "stations.csv"]
+        );
+        let columns = metadata.resources[0]
+            .schema
+            .as_ref()
+            .unwrap()
+            .get_processed_columns();
+        assert_eq!(
+            columns[1].description().map(String::as_str),
+            Some("Degrees Celsius")
+        );
+        assert_eq!(columns[1].get_type().map(String::as_str), Some("numeric"));
+    }
+}
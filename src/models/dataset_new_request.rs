@@ -1,7 +1,7 @@
-use crate::archive::ArchiveMode;
-use crate::error::KaggleError;
-use crate::models::metadata::Metadata;
+use crate::archive::{ArchiveMode, ArchiveOptions};
+use crate::models::metadata::DatasetMetadataFile;
 use crate::models::{DatasetUploadFile, License};
+use crate::upload::UploadOptions;
 use crate::KaggleApiClient;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
@@ -12,23 +12,23 @@ pub struct DatasetNew {
     /// does not have any resources.
     pub dataset_folder: Option<PathBuf>,
     /// The metadata of the dataset
-    pub metadata: Metadata,
+    pub metadata: DatasetMetadataFile,
     /// Whether or not the dataset should be private
     pub is_private: bool,
     /// Whether or not a tabular dataset should be converted to csv
     pub convert_to_csv: bool,
-    /// How to archive the files beforehand
-    pub archive_mode: ArchiveMode,
+    /// How to upload the files of the dataset folder
+    pub upload_options: UploadOptions,
 }
 
 impl DatasetNew {
-    pub fn with_metadata(metadata: Metadata) -> Self {
+    pub fn with_metadata(metadata: DatasetMetadataFile) -> Self {
         Self {
             dataset_folder: None,
             metadata,
             is_private: true,
             convert_to_csv: true,
-            archive_mode: Default::default(),
+            upload_options: Default::default(),
         }
     }
 
@@ -41,14 +41,6 @@ impl DatasetNew {
         Ok(new)
     }
 
-    pub(crate) fn validate_resources(&self) -> Result<(), KaggleError> {
-        if let Some(folder) = &self.dataset_folder {
-            self.metadata.validate_resource(folder)
-        } else {
-            Ok(())
-        }
-    }
-
     pub fn with_private(mut self, is_private: bool) -> Self {
         self.is_private = is_private;
         self
@@ -70,10 +62,40 @@ impl DatasetNew {
     }
 
     pub fn archive_mode(mut self, archive_mode: ArchiveMode) -> Self {
-        self.archive_mode = archive_mode;
+        self.upload_options.archive_mode = archive_mode;
         self
     }
 
+    pub fn archive_options(mut self, archive_options: ArchiveOptions) -> Self {
+        self.upload_options.archive_options = archive_options;
+        self
+    }
+
+    pub fn upload_options(mut self, upload_options: UploadOptions) -> Self {
+        self.upload_options = upload_options;
+        self
+    }
+
+    /// Skip files matching the gitignore-style pattern, in addition to the
+    /// patterns of the folder's `.kaggleignore` file.
+    pub fn ignore_pattern(mut self, ignore_pattern: impl ToString) -> Self {
+        self.upload_options
+            .ignore_patterns
+            .push(ignore_pattern.to_string());
+        self
+    }
+
+    /// The files of the dataset folder that would be uploaded, relative to the
+    /// folder.
+    pub fn upload_preview(&self) -> anyhow::Result<Vec<PathBuf>> {
+        if let Some(folder) = &self.dataset_folder {
+            self.upload_options
+                .preview(folder, &self.metadata.resources)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
     pub fn dataset_folder(mut self, dataset_folder: impl AsRef<Path>) -> Self {
         self.dataset_folder = Some(dataset_folder.as_ref().to_path_buf());
         self
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// The processing status of the latest version of a dataset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatasetStatus {
+    /// The version was accepted but processing has not started yet
+    Pending,
+    /// The files of the version are being processed
+    Processing,
+    /// The version is ready to use
+    Ready,
+    /// Processing failed with the server's message
+    Error(String),
+}
+
+impl DatasetStatus {
+    pub fn is_ready(&self) -> bool {
+        *self == DatasetStatus::Ready
+    }
+
+    /// Whether processing finished, either successfully or with an error.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, DatasetStatus::Ready | DatasetStatus::Error(_))
+    }
+
+    /// Parse the response of the `datasets/status` endpoint.
+    ///
+    /// The status is either a plain string like `"ready"` or an object with a
+    /// `status` field and an optional error message, unknown in-progress
+    /// states are reported as [`DatasetStatus::Processing`].
+    pub fn from_value(value: &Value) -> Self {
+        match value {
+            Value::Null => DatasetStatus::Pending,
+            Value::String(status) => Self::parse(status, None),
+            Value::Object(obj) => {
+                let msg = ["error", "errorMessage", "message"]
+                    .iter()
+                    .filter_map(|key| obj.get(*key).and_then(Value::as_str))
+                    .find(|msg| !msg.is_empty());
+                match obj.get("status").and_then(Value::as_str) {
+                    Some(status) => Self::parse(status, msg),
+                    None => match msg {
+                        Some(msg) => DatasetStatus::Error(msg.to_string()),
+                        None => DatasetStatus::Pending,
+                    },
+                }
+            }
+            _ => DatasetStatus::Processing,
+        }
+    }
+
+    fn parse(status: &str, msg: Option<&str>) -> Self {
+        match status.to_ascii_lowercase().as_str() {
+            "ready" | "complete" | "completed" => DatasetStatus::Ready,
+            "pending" | "queued" | "not_yet_persisted" => DatasetStatus::Pending,
+            "error" | "failed" | "deleted" => {
+                DatasetStatus::Error(msg.unwrap_or(status).to_string())
+            }
+            _ => DatasetStatus::Processing,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DatasetStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self::from_value(&Value::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_status() {
+        assert_eq!(
+            DatasetStatus::from_value(&json!("ready")),
+            DatasetStatus::Ready
+        );
+        assert_eq!(
+            DatasetStatus::from_value(&json!(null)),
+            DatasetStatus::Pending
+        );
+        assert_eq!(
+            DatasetStatus::from_value(&json!({"status": "BLOBS_RECEIVED"})),
+            DatasetStatus::Processing
+        );
+        assert_eq!(
+            DatasetStatus::from_value(&json!({"status": "error", "error": "bad file"})),
+            DatasetStatus::Error("bad file".to_string())
+        );
+        assert_eq!(
+            DatasetStatus::from_value(&json!("failed")),
+            DatasetStatus::Error("failed".to_string())
+        );
+    }
+}
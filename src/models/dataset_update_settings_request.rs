@@ -4,23 +4,26 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct DatasetUpdateSettingsRequest {
     /// Title of the dataset
+    #[serde(skip_serializing_if = "Option::is_none", default)]
     title: Option<String>,
     /// Subtitle of the dataset
+    #[serde(skip_serializing_if = "Option::is_none", default)]
     subtitle: Option<String>,
     /// Description of the dataset
+    #[serde(skip_serializing_if = "Option::is_none", default)]
     description: Option<String>,
     /// Whether or not the dataset should be private
-    #[serde(rename = "isPrivate")]
+    #[serde(rename = "isPrivate", skip_serializing_if = "Option::is_none", default)]
     is_private: Option<bool>,
     /// A list of licenses that apply to this dataset
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    licenses: Vec<License>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    licenses: Option<Vec<License>>,
     /// A list of keywords that apply to this dataset
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    keywords: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    keywords: Option<Vec<String>>,
     /// A list of collaborators that may read or edit this dataset
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    collaborators: Vec<Collaborator>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    collaborators: Option<Vec<Collaborator>>,
     /// A list containing metadata for each file in the dataset
     #[serde(skip_serializing_if = "Option::is_none", default)]
     data: Option<serde_json::Value>,
@@ -104,47 +107,59 @@ impl DatasetUpdateSettingsRequest {
     }
 
     pub fn set_licenses(&mut self, licenses: Vec<License>) {
-        self.licenses = licenses;
+        self.licenses = Some(licenses);
     }
 
     pub fn with_licenses(mut self, licenses: Vec<License>) -> DatasetUpdateSettingsRequest {
-        self.licenses = licenses;
+        self.licenses = Some(licenses);
         self
     }
 
-    pub fn licenses(&self) -> &Vec<License> {
+    pub fn licenses(&self) -> Option<&Vec<License>> {
         self.licenses.as_ref()
     }
 
+    pub fn reset_licenses(&mut self) {
+        self.licenses = None;
+    }
+
     pub fn set_keywords(&mut self, keywords: Vec<String>) {
-        self.keywords = keywords;
+        self.keywords = Some(keywords);
     }
 
     pub fn with_keywords(mut self, keywords: Vec<String>) -> DatasetUpdateSettingsRequest {
-        self.keywords = keywords;
+        self.keywords = Some(keywords);
         self
     }
 
-    pub fn keywords(&self) -> &Vec<String> {
+    pub fn keywords(&self) -> Option<&Vec<String>> {
         self.keywords.as_ref()
     }
 
+    pub fn reset_keywords(&mut self) {
+        self.keywords = None;
+    }
+
     pub fn set_collaborators(&mut self, collaborators: Vec<Collaborator>) {
-        self.collaborators = collaborators;
+        self.collaborators = Some(collaborators);
     }
 
     pub fn with_collaborators(
         mut self,
         collaborators: Vec<Collaborator>,
     ) -> DatasetUpdateSettingsRequest {
-        self.collaborators = collaborators;
+        self.collaborators = Some(collaborators);
         self
     }
 
-    pub fn collaborators(&self) -> &Vec<Collaborator> {
+    pub fn collaborators(&self) -> Option<&Vec<Collaborator>> {
         self.collaborators.as_ref()
     }
 
+    pub fn reset_collaborators(&mut self) {
+        self.collaborators = None;
+    }
+
     pub fn set_data(&mut self, data: serde_json::Value) {
         self.data = Some(data);
     }
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::models::DatasetColumn;
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct DatasetUploadFile {
     /// A token referencing a specific file upload that can be used across
     /// requests
@@ -0,0 +1,429 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::archive::sha256_digest;
+use crate::error::KaggleError;
+use crate::models::extended::{DatasetFile, DatasetMetadataInfo};
+use crate::models::metadata::DatasetMetadataFile;
+use crate::upload::UploadOptions;
+
+/// A dry-run of a new dataset version, created by
+/// [`KaggleApiClient::dataset_version_plan`].
+///
+/// Lists how the files of the local dataset folder differ from the files of
+/// the latest remote version and which metadata fields the new version would
+/// change, without uploading anything.
+///
+/// [`KaggleApiClient::dataset_version_plan`]: crate::KaggleApiClient::dataset_version_plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatasetVersionPlan {
+    /// The `owner/slug` identifier of the dataset
+    pub dataset: String,
+    /// The local dataset folder
+    pub folder: PathBuf,
+    /// Local files that don't exist in the remote dataset
+    pub added: Vec<PlannedFile>,
+    /// Remote files that don't exist locally and will be removed
+    pub removed: Vec<PlannedFile>,
+    /// Files that exist on both sides but differ in size or hash
+    pub changed: Vec<PlannedFile>,
+    /// Files that exist on both sides with the same size and hash
+    pub unchanged: Vec<PlannedFile>,
+    /// Metadata fields that are updated with the new version
+    pub metadata_changes: Vec<FieldChange>,
+    /// The options that select and upload the local files, these are not
+    /// serialized so a deserialized plan uses the default options
+    #[serde(skip)]
+    pub upload_options: UploadOptions,
+}
+
+/// A single file of a [`DatasetVersionPlan`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedFile {
+    /// The name of the file in the dataset
+    pub name: String,
+    /// The local file or directory, `None` for removed files
+    pub path: Option<PathBuf>,
+    /// The size of the local file, `None` for directories and removed files
+    pub local_bytes: Option<u64>,
+    /// The size of the remote file, `None` for added files
+    pub remote_bytes: Option<u64>,
+    /// The hex encoded SHA-256 digest of the local file, only computed if
+    /// the plan compares hashes
+    pub sha256: Option<String>,
+    /// The name of the directory in the dataset if this is an archived
+    /// directory, which Kaggle extracts into `<directory>/...`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    /// The sizes of the files of an archived directory by their name in the
+    /// dataset
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub members: BTreeMap<String, u64>,
+}
+
+/// A metadata field whose local value differs from the remote value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    /// The name of the field in the `dataset-metadata.json`
+    pub field: String,
+    /// The current value of the remote dataset
+    pub remote: Value,
+    /// The new value of the local metadata
+    pub local: Value,
+}
+
+impl DatasetVersionPlan {
+    /// Compare the `local` files with the `remote` files by name and size.
+    ///
+    /// `known_hashes` maps file names to the SHA-256 digests of the remote
+    /// files, e.g. from [`DatasetVersionPlan::file_hashes`] of a previous
+    /// plan. Files with the same size but a different known hash are
+    /// considered changed.
+    ///
+    /// Archived directories are compared by their members with the remote
+    /// files inside the directory, they are unchanged if all files have the
+    /// same names and sizes.
+    pub fn new(
+        dataset: impl ToString,
+        folder: impl AsRef<Path>,
+        local: Vec<PlannedFile>,
+        remote: &[DatasetFile],
+        known_hashes: Option<&HashMap<String, String>>,
+    ) -> Self {
+        let mut remote: HashMap<_, _> = remote
+            .iter()
+            .map(|f| (f.name.as_str(), f.total_bytes.max(0) as u64))
+            .collect();
+
+        let mut plan = Self {
+            dataset: dataset.to_string(),
+            folder: folder.as_ref().to_path_buf(),
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+            unchanged: Vec::new(),
+            metadata_changes: Vec::new(),
+            upload_options: UploadOptions::default(),
+        };
+
+        for mut file in local {
+            if let Some(directory) = &file.directory {
+                let prefix = format!("{}/", directory);
+                let names: Vec<_> = remote
+                    .keys()
+                    .filter(|name| name.starts_with(&prefix))
+                    .copied()
+                    .collect();
+                let remote_members: BTreeMap<_, _> = names
+                    .into_iter()
+                    .filter_map(|name| Some((name.to_string(), remote.remove(name)?)))
+                    .collect();
+                if remote_members.is_empty() {
+                    plan.added.push(file);
+                } else {
+                    file.remote_bytes = Some(remote_members.values().sum());
+                    if remote_members == file.members {
+                        plan.unchanged.push(file);
+                    } else {
+                        plan.changed.push(file);
+                    }
+                }
+                continue;
+            }
+            let remote_bytes = match remote.remove(file.name.as_str()) {
+                Some(bytes) => bytes,
+                None => {
+                    plan.added.push(file);
+                    continue;
+                }
+            };
+            file.remote_bytes = Some(remote_bytes);
+            let same_hash = match (&file.sha256, known_hashes.and_then(|h| h.get(&file.name))) {
+                (Some(local), Some(known)) => local.eq_ignore_ascii_case(known),
+                _ => true,
+            };
+            if file.local_bytes == Some(remote_bytes) && same_hash {
+                plan.unchanged.push(file);
+            } else {
+                plan.changed.push(file);
+            }
+        }
+
+        let mut removed: Vec<_> = remote.into_iter().collect();
+        removed.sort();
+        plan.removed = removed
+            .into_iter()
+            .map(|(name, bytes)| PlannedFile {
+                name: name.to_string(),
+                path: None,
+                local_bytes: None,
+                remote_bytes: Some(bytes),
+                sha256: None,
+                directory: None,
+                members: BTreeMap::new(),
+            })
+            .collect();
+        plan
+    }
+
+    /// Set the metadata fields of the `local` metadata that differ from the
+    /// `remote` dataset.
+    ///
+    /// Only the fields that are sent with a new version are compared: the
+    /// subtitle, the description and the keywords.
+    pub fn with_metadata(
+        mut self,
+        local: &DatasetMetadataFile,
+        remote: &DatasetMetadataInfo,
+    ) -> Self {
+        let mut changes = Vec::new();
+        if let Some(subtitle) = &local.subtitle {
+            if *subtitle != remote.subtitle {
+                changes.push(FieldChange::new("subtitle", &remote.subtitle, subtitle));
+            }
+        }
+        if let Some(description) = &local.description {
+            if *description != remote.description {
+                changes.push(FieldChange::new(
+                    "description",
+                    &remote.description,
+                    description,
+                ));
+            }
+        }
+        let mut local_keywords = local.keywords.clone();
+        let mut remote_keywords = remote.keywords.clone();
+        local_keywords.sort();
+        remote_keywords.sort();
+        if local_keywords != remote_keywords {
+            changes.push(FieldChange::new(
+                "keywords",
+                &remote.keywords,
+                &local.keywords,
+            ));
+        }
+        self.metadata_changes = changes;
+        self
+    }
+
+    /// Set the options the local files were selected with, which are used to
+    /// upload them as well.
+    pub fn with_upload_options(mut self, upload_options: UploadOptions) -> Self {
+        self.upload_options = upload_options;
+        self
+    }
+
+    /// Whether the new version would neither change any file nor any
+    /// metadata field.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.metadata_changes.is_empty()
+    }
+
+    /// All local files that are uploaded with the new version.
+    pub fn local_files(&self) -> impl Iterator<Item = &PlannedFile> {
+        self.added
+            .iter()
+            .chain(self.changed.iter())
+            .chain(self.unchanged.iter())
+    }
+
+    /// The SHA-256 digests of all local files by name, to be passed as known
+    /// hashes to the plan of the next version.
+    pub fn file_hashes(&self) -> HashMap<String, String> {
+        self.local_files()
+            .filter_map(|f| f.sha256.clone().map(|h| (f.name.clone(), h)))
+            .collect()
+    }
+
+    /// Check that `local`, the files that would be uploaded now, are exactly
+    /// the planned local files.
+    pub fn verify_files(&self, local: &[PlannedFile]) -> anyhow::Result<()> {
+        let key = |f: &PlannedFile| (f.name.clone(), f.path.clone());
+        let planned: Vec<_> = self.local_files().map(key).collect();
+        let current: Vec<_> = local.iter().map(key).collect();
+        let unplanned: Vec<_> = current
+            .iter()
+            .filter(|f| !planned.contains(f))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let missing: Vec<_> = planned
+            .iter()
+            .filter(|f| !current.contains(f))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if unplanned.is_empty() && missing.is_empty() {
+            Ok(())
+        } else {
+            Err(KaggleError::meta(format!(
+                "The files to upload differ from the version plan, not planned: [{}], missing: [{}]",
+                unplanned.join(", "),
+                missing.join(", ")
+            ))
+            .into())
+        }
+    }
+
+    /// Check that the local files still match the plan.
+    ///
+    /// Returns an error if a planned file was removed or its size or digest
+    /// changed since the plan was created.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        for file in self.local_files() {
+            let path = match &file.path {
+                Some(path) => path,
+                None => continue,
+            };
+            let stale = || {
+                KaggleError::meta(format!(
+                    "{} changed since the version plan was created",
+                    path.display()
+                ))
+            };
+            if !path.exists() {
+                return Err(stale().into());
+            }
+            if let Some(bytes) = file.local_bytes {
+                if path.metadata()?.len() != bytes {
+                    return Err(stale().into());
+                }
+            }
+            if let Some(hash) = &file.sha256 {
+                if sha256_digest(path)? != *hash {
+                    return Err(stale().into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FieldChange {
+    pub(crate) fn new<R: Serialize + ?Sized, L: Serialize + ?Sized>(
+        field: &str,
+        remote: &R,
+        local: &L,
+    ) -> Self {
+        Self {
+            field: field.to_string(),
+            remote: serde_json::to_value(remote).unwrap_or_default(),
+            local: serde_json::to_value(local).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(name: &str, total_bytes: i64) -> DatasetFile {
+        serde_json::from_value(serde_json::json!({
+            "ref": name,
+            "creationDate": "2020-01-01T00:00:00.000Z",
+            "datasetRef": "owner/data",
+            "description": null,
+            "fileType": ".csv",
+            "name": name,
+            "ownerRef": "owner",
+            "totalBytes": total_bytes,
+            "url": "",
+            "columns": []
+        }))
+        .unwrap()
+    }
+
+    fn local(name: &str, bytes: u64, sha256: &str) -> PlannedFile {
+        PlannedFile {
+            name: name.to_string(),
+            path: Some(PathBuf::from(name)),
+            local_bytes: Some(bytes),
+            remote_bytes: None,
+            sha256: Some(sha256.to_string()),
+            directory: None,
+            members: BTreeMap::new(),
+        }
+    }
+
+    fn archived(directory: &str, members: &[(&str, u64)]) -> PlannedFile {
+        PlannedFile {
+            name: format!("{}.zip", directory),
+            path: Some(PathBuf::from(directory)),
+            local_bytes: None,
+            remote_bytes: None,
+            sha256: None,
+            directory: Some(directory.to_string()),
+            members: members
+                .iter()
+                .map(|(name, bytes)| (format!("{}/{}", directory, name), *bytes))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn diff_archived_directories() {
+        let plan = DatasetVersionPlan::new(
+            "owner/data",
+            ".",
+            vec![
+                archived("images", &[("cat.jpg", 3), ("dog.jpg", 4)]),
+                archived("labels", &[("train.csv", 10)]),
+                archived("audio", &[("bark.wav", 7)]),
+            ],
+            &[
+                remote("images/cat.jpg", 3),
+                remote("images/dog.jpg", 4),
+                remote("labels/train.csv", 10),
+                remote("labels/test.csv", 5),
+                remote("images.csv", 1),
+            ],
+            None,
+        );
+
+        let names =
+            |files: &[PlannedFile]| files.iter().map(|f| f.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&plan.unchanged), vec!["images.zip"]);
+        assert_eq!(names(&plan.changed), vec!["labels.zip"]);
+        assert_eq!(names(&plan.added), vec!["audio.zip"]);
+        assert_eq!(names(&plan.removed), vec!["images.csv"]);
+        assert_eq!(plan.unchanged[0].remote_bytes, Some(7));
+        assert_eq!(plan.changed[0].remote_bytes, Some(15));
+    }
+
+    #[test]
+    fn diff_files() {
+        let mut known = HashMap::new();
+        known.insert("b.csv".to_string(), "old".to_string());
+        let plan = DatasetVersionPlan::new(
+            "owner/data",
+            ".",
+            vec![
+                local("a.csv", 10, "a"),
+                local("b.csv", 20, "new"),
+                local("c.csv", 30, "c"),
+                local("new.csv", 1, "n"),
+            ],
+            &[
+                remote("a.csv", 10),
+                remote("b.csv", 20),
+                remote("c.csv", 31),
+                remote("gone.csv", 5),
+            ],
+            Some(&known),
+        );
+
+        let names =
+            |files: &[PlannedFile]| files.iter().map(|f| f.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&plan.added), vec!["new.csv"]);
+        assert_eq!(names(&plan.removed), vec!["gone.csv"]);
+        assert_eq!(names(&plan.changed), vec!["b.csv", "c.csv"]);
+        assert_eq!(names(&plan.unchanged), vec!["a.csv"]);
+        assert_eq!(plan.changed[1].remote_bytes, Some(31));
+        assert!(!plan.is_empty());
+    }
+}
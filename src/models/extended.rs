@@ -1,8 +1,10 @@
-use crate::models::{Collaborator, DatasetColumn, License};
+use crate::error::KaggleError;
+use crate::models::{Collaborator, DatasetColumn, KernelRef, License};
 use crate::query::{KernelType, Language, PushKernelType};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::convert::TryInto;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -177,36 +179,80 @@ pub struct FileUploadInfo {
     pub create_url: String,
 }
 
+/// The raw response of creating a new dataset or dataset version.
+///
+/// Kaggle reports a rejected request with an `error` status and a successful
+/// response code, [`DatasetNewResponse::into_result`] turns it into an error.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DatasetNewVersionResponse {
-    #[serde(flatten)]
-    pub extra: HashMap<String, serde_json::Value>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DatasetNewResponse {
     /// If an error occurred, this is None
-    #[serde(rename = "ref")]
+    #[serde(rename = "ref", default)]
     pub ref_: Option<String>,
-    pub url: String,
-    pub status: String,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
     pub error: Option<String>,
-    #[serde(rename = "invalidTags")]
-    pub invalid_tags: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub invalid_tags: Vec<String>,
+    #[serde(default)]
+    pub version_number: Option<i32>,
 }
 
+/// The response of creating a new dataset version, it has the same shape as
+/// the response of creating a new dataset.
+pub type DatasetNewVersionResponse = DatasetNewResponse;
+
 impl DatasetNewResponse {
     pub fn is_success(&self) -> bool {
-        self.status == "ok"
+        !self.is_error() && self.ref_.is_some()
     }
 
     pub fn is_error(&self) -> bool {
-        self.status == "error"
+        self.status.as_deref() == Some("error") || self.cause().is_some()
     }
 
     pub fn cause(&self) -> Option<&String> {
-        self.error.as_ref()
+        self.error.as_ref().filter(|err| !err.is_empty())
     }
+
+    /// The created dataset, or a [`KaggleError::DatasetRejected`] with the
+    /// server's message if Kaggle reported a failure.
+    pub fn into_result(self) -> Result<DatasetCreated, KaggleError> {
+        match self.ref_ {
+            Some(ref_) if !self.is_error() => Ok(DatasetCreated {
+                url: self.url.unwrap_or_default(),
+                ref_,
+                version_number: self.version_number,
+                invalid_tags: self.invalid_tags,
+            }),
+            _ => Err(KaggleError::DatasetRejected {
+                msg: self
+                    .error
+                    .filter(|err| !err.is_empty())
+                    .or(self.status)
+                    .unwrap_or_else(|| "unknown error".to_string()),
+                invalid_tags: self.invalid_tags,
+            }),
+        }
+    }
+}
+
+/// A dataset or dataset version that was created successfully.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatasetCreated {
+    /// The `owner/slug` identifier of the dataset
+    #[serde(rename = "ref")]
+    pub ref_: String,
+    /// The url of the dataset
+    pub url: String,
+    /// The number of the new version, if reported by Kaggle
+    pub version_number: Option<i32>,
+    /// Keywords of the metadata that Kaggle didn't accept
+    pub invalid_tags: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -214,6 +260,9 @@ impl DatasetNewResponse {
 pub struct ListFilesResult {
     pub error_message: Option<String>,
     pub dataset_files: Vec<DatasetFile>,
+    /// The token of the next page of files, `None` or empty on the last page
+    #[serde(default)]
+    pub next_page_token: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -313,11 +362,42 @@ pub struct KernelPushResponse {
     pub extra: HashMap<String, serde_json::Value>,
 }
 
+impl KernelPushResponse {
+    /// The error message if Kaggle rejected the push.
+    pub fn error(&self) -> Option<&str> {
+        self.extra
+            .get("error")
+            .and_then(serde_json::Value::as_str)
+            .filter(|err| !err.is_empty())
+    }
+
+    /// The pushed kernel, parsed from the `url` or the `ref` of the response.
+    pub fn kernel(&self) -> Option<KernelRef> {
+        ["url", "ref"]
+            .iter()
+            .filter_map(|key| self.extra.get(*key)?.as_str())
+            .find_map(|s| {
+                s.trim_start_matches('/')
+                    .trim_start_matches("code/")
+                    .parse()
+                    .ok()
+            })
+    }
+
+    /// The number of the version that was pushed.
+    pub fn version_number(&self) -> Option<u32> {
+        self.extra.get("versionNumber")?.as_u64()?.try_into().ok()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KernelOutput {
     #[serde(default)]
     pub files: Vec<KernelOutputFile>,
     pub log: Option<String>,
+    /// The token of the next page of files, `None` or empty on the last page
+    #[serde(rename = "nextPageToken", default)]
+    pub next_page_token: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -332,6 +412,16 @@ pub struct DownloadResponse {
     pub content: String,
 }
 
+impl DownloadResponse {
+    /// The download URL, or `None` if the `content` is the inline content of
+    /// a small file.
+    pub fn url(&self) -> Option<reqwest::Url> {
+        reqwest::Url::parse(&self.content)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+    }
+}
+
 mod date_serializer {
     use chrono::{DateTime, NaiveDateTime};
     use serde::de::Error;
@@ -402,4 +492,25 @@ mod tests {
         let _: DatasetMetadata =
             serde_json::from_str(DATASET_META_DATA).expect("failed to parse dataset metadata");
     }
+
+    #[test]
+    fn dataset_new_response_result() {
+        let ok: DatasetNewResponse = serde_json::from_str(
+            r#"{"ref": "me/data", "url": "https://www.kaggle.com/me/data", "status": "ok", "error": null, "invalidTags": ["nope"], "versionNumber": 3}"#,
+        )
+        .unwrap();
+        let created = ok.into_result().unwrap();
+        assert_eq!(created.ref_, "me/data");
+        assert_eq!(created.version_number, Some(3));
+        assert_eq!(created.invalid_tags, vec!["nope"]);
+
+        let err: DatasetNewResponse = serde_json::from_str(
+            r#"{"ref": "", "url": "", "status": "error", "error": "The slug is taken", "invalidTags": []}"#,
+        )
+        .unwrap();
+        match err.into_result() {
+            Err(KaggleError::DatasetRejected { msg, .. }) => assert_eq!(msg, "The slug is taken"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
 }
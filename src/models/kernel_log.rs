@@ -0,0 +1,274 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The stream a log entry was written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+    #[serde(other)]
+    Other,
+}
+
+/// A chunk of output of a kernel run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub stream_name: LogStream,
+    /// Seconds since the start of the run
+    pub time: f64,
+    pub data: String,
+}
+
+/// The log of a kernel run, as returned in [`KernelOutput::log`].
+///
+/// [`KernelOutput::log`]: crate::models::extended::KernelOutput::log
+///
+/// Example
+///
+/// ```no_run
+/// use kaggle::KaggleApiClient;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let kaggle = KaggleApiClient::builder().build()?;
+///     if let Some(log) = kaggle.kernel_log("owner/kernel").await? {
+///         if let Some(err) = log.first_error() {
+///             eprintln!("{}", err);
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct KernelLog {
+    pub entries: Vec<LogEntry>,
+}
+
+impl KernelLog {
+    /// All entries of a single stream.
+    pub fn stream(&self, stream: LogStream) -> impl Iterator<Item = &LogEntry> {
+        self.entries.iter().filter(move |e| e.stream_name == stream)
+    }
+
+    /// Everything written to stdout.
+    pub fn stdout(&self) -> String {
+        self.stream(LogStream::Stdout).map(|e| &*e.data).collect()
+    }
+
+    /// Everything written to stderr.
+    pub fn stderr(&self) -> String {
+        self.stream(LogStream::Stderr).map(|e| &*e.data).collect()
+    }
+
+    /// Render the entries as plain text, every line prefixed with the seconds
+    /// since the first entry.
+    ///
+    /// If `stream` is set, only the entries of that stream are rendered.
+    pub fn to_text(&self, stream: Option<LogStream>) -> String {
+        let start = self.entries.first().map(|e| e.time).unwrap_or_default();
+        let mut text = String::new();
+        for entry in self
+            .entries
+            .iter()
+            .filter(|e| stream.is_none_or(|s| e.stream_name == s))
+        {
+            for line in entry.data.lines() {
+                text.push_str(&format!(
+                    "[{:>8.1}s] {}\n",
+                    entry.time - start,
+                    strip_ansi(line)
+                ));
+            }
+        }
+        text
+    }
+
+    /// All Python tracebacks in the log, ordered by time.
+    ///
+    /// Both plain Python and IPython style tracebacks are detected, color
+    /// codes are removed.
+    pub fn tracebacks(&self) -> Vec<Traceback> {
+        let mut tracebacks: Vec<_> = [LogStream::Stderr, LogStream::Stdout, LogStream::Other]
+            .iter()
+            .flat_map(|stream| find_tracebacks(&self.lines(*stream)))
+            .collect();
+        tracebacks.sort_by(|a, b| a.time.total_cmp(&b.time));
+        tracebacks
+    }
+
+    /// The first traceback in the log.
+    pub fn first_error(&self) -> Option<Traceback> {
+        self.tracebacks().into_iter().next()
+    }
+
+    /// The lines of a stream with the time of the entry they start in.
+    ///
+    /// Lines can be split across several entries, so the data of all
+    /// entries is joined before splitting.
+    fn lines(&self, stream: LogStream) -> Vec<(f64, String)> {
+        let mut lines = Vec::new();
+        let mut current: Option<(f64, String)> = None;
+        for entry in self.stream(stream) {
+            for part in entry.data.split_inclusive('\n') {
+                let (_, line) = current.get_or_insert_with(|| (entry.time, String::new()));
+                line.push_str(part);
+                if part.ends_with('\n') {
+                    lines.extend(current.take());
+                }
+            }
+        }
+        lines.extend(current);
+        lines
+            .into_iter()
+            .map(|(time, line)| (time, strip_ansi(line.trim_end_matches(['\n', '\r']))))
+            .collect()
+    }
+}
+
+impl FromStr for KernelLog {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+/// A Python traceback found in a kernel log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Traceback {
+    /// Seconds since the start of the run
+    pub time: f64,
+    /// The name of the exception, like `ValueError`
+    pub exception: String,
+    /// The message of the exception, may be empty
+    pub message: String,
+    /// All lines of the traceback, including the final exception line
+    pub lines: Vec<String>,
+}
+
+impl fmt::Display for Traceback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.lines.join("\n"))
+    }
+}
+
+const TRACEBACK_HEADER: &str = "Traceback (most recent call last)";
+
+fn find_tracebacks(lines: &[(f64, String)]) -> Vec<Traceback> {
+    let mut tracebacks = Vec::new();
+    let mut idx = 0;
+    while idx < lines.len() {
+        let (time, header) = &lines[idx];
+        idx += 1;
+        if !header.contains(TRACEBACK_HEADER) {
+            continue;
+        }
+        let mut body = vec![header.clone()];
+        while idx < lines.len() {
+            let line = &lines[idx].1;
+            if line.contains(TRACEBACK_HEADER) {
+                break;
+            }
+            body.push(line.clone());
+            idx += 1;
+            if let Some((exception, message)) = exception_line(line) {
+                tracebacks.push(Traceback {
+                    time: *time,
+                    exception: exception.to_string(),
+                    message: message.to_string(),
+                    lines: body,
+                });
+                break;
+            }
+        }
+    }
+    tracebacks
+}
+
+/// Split a line like `ValueError: invalid value` into the exception name and
+/// its message.
+fn exception_line(line: &str) -> Option<(&str, &str)> {
+    let (name, message) = match line.split_once(':') {
+        Some((name, message)) => (name, message.trim()),
+        None => (line.trim_end(), ""),
+    };
+    let is_name = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    if is_name {
+        Some((name, message))
+    } else {
+        None
+    }
+}
+
+/// Remove ANSI color codes.
+fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = r#"[
+        {"stream_name": "stdout", "time": 2.5, "data": "loading data\n"},
+        {"stream_name": "stderr", "time": 3.0, "data": "Traceback (most recent call last):\n  File \"script.py\", line 3, in <module>\n"},
+        {"stream_name": "stdout", "time": 3.1, "data": "still running\n"},
+        {"stream_name": "stderr", "time": 3.2, "data": "    main()\nValueError: invalid literal\n"}
+    ]"#;
+
+    #[test]
+    fn parse_and_render() {
+        let log: KernelLog = LOG.parse().unwrap();
+        assert_eq!(log.entries.len(), 4);
+        assert_eq!(log.stdout(), "loading data\nstill running\n");
+        assert_eq!(
+            log.to_text(Some(LogStream::Stdout)),
+            "[     0.0s] loading data\n[     0.6s] still running\n"
+        );
+    }
+
+    #[test]
+    fn detect_tracebacks() {
+        let log: KernelLog = LOG.parse().unwrap();
+        let err = log.first_error().unwrap();
+        assert_eq!(err.time, 3.0);
+        assert_eq!(err.exception, "ValueError");
+        assert_eq!(err.message, "invalid literal");
+        assert_eq!(err.lines.len(), 4);
+
+        let ipython: KernelLog = r#"[{"stream_name": "stderr", "time": 1.0, "data":
+            "\u001b[0;31mKeyError\u001b[0m    Traceback (most recent call last)\nCell In[3], line 1\n----> 1 df['x']\n\u001b[0;31mKeyError\u001b[0m: 'x'\n"}]"#
+            .parse()
+            .unwrap();
+        let err = ipython.first_error().unwrap();
+        assert_eq!(err.exception, "KeyError");
+        assert_eq!(err.message, "'x'");
+
+        let clean: KernelLog = r#"[{"stream_name": "stdout", "time": 1.0, "data": "ok\n"}]"#
+            .parse()
+            .unwrap();
+        assert!(clean.first_error().is_none());
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// The status of the latest run of a kernel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KernelStatus {
+    /// The run is waiting for a worker
+    Queued,
+    /// The kernel is running
+    Running,
+    /// The run finished successfully
+    Complete,
+    /// The run failed with the server's failure message
+    Error(String),
+    /// Cancelling the run was requested but not yet acknowledged
+    CancelRequested,
+    /// The run was cancelled
+    Cancelled,
+}
+
+impl KernelStatus {
+    pub fn is_complete(&self) -> bool {
+        *self == KernelStatus::Complete
+    }
+
+    /// Whether the run ended, either successfully, with an error or because
+    /// it was cancelled.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            KernelStatus::Complete | KernelStatus::Error(_) | KernelStatus::Cancelled
+        )
+    }
+
+    /// Parse the response of the `kernels/status` endpoint.
+    ///
+    /// The response is an object with a `status` like `"running"` and a
+    /// `failureMessage`, unknown states are reported as
+    /// [`KernelStatus::Running`].
+    pub fn from_value(value: &Value) -> Self {
+        match value {
+            Value::String(status) => Self::parse(status, None),
+            Value::Object(obj) => {
+                let msg = ["failureMessage", "error", "errorMessage"]
+                    .iter()
+                    .filter_map(|key| obj.get(*key).and_then(Value::as_str))
+                    .find(|msg| !msg.is_empty());
+                match obj.get("status").and_then(Value::as_str) {
+                    Some(status) => Self::parse(status, msg),
+                    None => match msg {
+                        Some(msg) => KernelStatus::Error(msg.to_string()),
+                        None => KernelStatus::Queued,
+                    },
+                }
+            }
+            _ => KernelStatus::Queued,
+        }
+    }
+
+    fn parse(status: &str, msg: Option<&str>) -> Self {
+        let status = status.to_ascii_lowercase().replace(['_', '-', ' '], "");
+        match status.as_str() {
+            "queued" | "newscript" => KernelStatus::Queued,
+            "complete" | "completed" => KernelStatus::Complete,
+            "error" | "failed" => KernelStatus::Error(msg.unwrap_or("error").to_string()),
+            "cancelrequested" => KernelStatus::CancelRequested,
+            "cancelacknowledged" | "cancelled" | "canceled" => KernelStatus::Cancelled,
+            _ => KernelStatus::Running,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for KernelStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self::from_value(&Value::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_status() {
+        assert_eq!(
+            KernelStatus::from_value(&json!({"status": "running", "failureMessage": null})),
+            KernelStatus::Running
+        );
+        assert_eq!(
+            KernelStatus::from_value(&json!({"status": "QUEUED"})),
+            KernelStatus::Queued
+        );
+        assert_eq!(
+            KernelStatus::from_value(&json!({"status": "complete"})),
+            KernelStatus::Complete
+        );
+        assert_eq!(
+            KernelStatus::from_value(&json!({"status": "error", "failureMessage": "OOM"})),
+            KernelStatus::Error("OOM".to_string())
+        );
+        assert_eq!(
+            KernelStatus::from_value(&json!({"status": "cancelRequested"})),
+            KernelStatus::CancelRequested
+        );
+        assert_eq!(
+            KernelStatus::from_value(&json!({"status": "CANCEL_ACKNOWLEDGED"})),
+            KernelStatus::Cancelled
+        );
+    }
+}
@@ -2,6 +2,13 @@ use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializ
 use std::fmt;
 use std::str::FromStr;
 
+use crate::error::KaggleError;
+
+/// The license of a dataset, kernel or model.
+///
+/// Names that are not known to this client are kept as
+/// [`License::Custom`], so they survive a round trip through the API
+/// unchanged.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum License {
     /// CC0-1.0
@@ -28,23 +35,234 @@ pub enum License {
     RedditApi,
     /// world-bank
     WorldBank,
+    /// CC-BY-4.0
+    CcBy40,
+    /// CC-BY-NC-4.0
+    CcByNc40,
+    /// PDDL
+    Pddl,
+    /// CC-BY-3.0
+    CcBy30,
+    /// CC-BY-3.0-IGO
+    CcBy30Igo,
+    /// US-Government-Works
+    UsGovernmentWorks,
+    /// CC-BY-NC-SA-3.0-IGO
+    CcByNcSa30Igo,
+    /// CDLA-Permissive-1.0
+    CdlaPermissive10,
+    /// CDLA-Sharing-1.0
+    CdlaSharing10,
+    /// CC-BY-ND-4.0
+    CcByNd40,
+    /// CC-BY-NC-ND-4.0
+    CcByNcNd40,
+    /// ODC-BY-1.0
+    OdcBy10,
+    /// LGPL-3.0
+    Lgpl30,
+    /// AGPL-3.0
+    Agpl30,
+    /// FDL-1.3
+    Fdl13,
+    /// EU-ODP-Legal-Notice
+    EuOdpLegalNotice,
+    /// apache-2.0
+    Apache20,
+    /// GPL-3.0
+    Gpl30,
+    /// MIT
+    Mit,
+    /// Any other license name, kept as is
+    Custom(String),
+}
+
+/// The kinds of Kaggle resources a license can be attached to.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LicenseTarget {
+    Dataset,
+    Kernel,
+    Model,
+}
+
+impl License {
+    /// All licenses with a known name.
+    pub const KNOWN: &'static [License] = &[
+        License::Cc010,
+        License::CcBySa40,
+        License::Gpl20,
+        License::OdbL10,
+        License::CcByNcSa40,
+        License::Unknown,
+        License::DbCl10,
+        License::CcBySa30,
+        License::CopyrightAuthors,
+        License::Other,
+        License::RedditApi,
+        License::WorldBank,
+        License::CcBy40,
+        License::CcByNc40,
+        License::Pddl,
+        License::CcBy30,
+        License::CcBy30Igo,
+        License::UsGovernmentWorks,
+        License::CcByNcSa30Igo,
+        License::CdlaPermissive10,
+        License::CdlaSharing10,
+        License::CcByNd40,
+        License::CcByNcNd40,
+        License::OdcBy10,
+        License::Lgpl30,
+        License::Agpl30,
+        License::Fdl13,
+        License::EuOdpLegalNotice,
+        License::Apache20,
+        License::Gpl30,
+        License::Mit,
+    ];
+
+    /// The license names Kaggle accepts for datasets.
+    const DATASET_NAMES: &'static [&'static str] = &[
+        "CC0-1.0",
+        "CC-BY-SA-4.0",
+        "GPL-2.0",
+        "ODbL-1.0",
+        "CC-BY-NC-SA-4.0",
+        "unknown",
+        "DbCL-1.0",
+        "CC-BY-SA-3.0",
+        "copyright-authors",
+        "other",
+        "reddit-api",
+        "world-bank",
+        "CC-BY-4.0",
+        "CC-BY-NC-4.0",
+        "PDDL",
+        "CC-BY-3.0",
+        "CC-BY-3.0-IGO",
+        "US-Government-Works",
+        "CC-BY-NC-SA-3.0-IGO",
+        "CDLA-Permissive-1.0",
+        "CDLA-Sharing-1.0",
+        "CC-BY-ND-4.0",
+        "CC-BY-NC-ND-4.0",
+        "ODC-BY-1.0",
+        "LGPL-3.0",
+        "AGPL-3.0",
+        "FDL-1.3",
+        "EU-ODP-Legal-Notice",
+        "apache-2.0",
+        "GPL-3.0",
+    ];
+
+    /// Kernels are always published under the Apache 2.0 license.
+    const KERNEL_NAMES: &'static [&'static str] = &["apache-2.0", "Apache 2.0"];
+
+    /// The license names Kaggle accepts for models.
+    const MODEL_NAMES: &'static [&'static str] = &[
+        "Apache 2.0",
+        "apache-2.0",
+        "MIT",
+        "CC0-1.0",
+        "CC-BY-4.0",
+        "CC-BY-SA-4.0",
+        "CC-BY-NC-4.0",
+        "CC-BY-NC-SA-4.0",
+        "CC-BY-ND-4.0",
+        "CC-BY-NC-ND-4.0",
+        "GPL-2.0",
+        "GPL-3.0",
+        "LGPL-3.0",
+        "AGPL-3.0",
+        "other",
+    ];
+
+    /// The name of the license as it is sent to Kaggle.
+    pub fn name(&self) -> &str {
+        match self {
+            License::Cc010 => "CC0-1.0",
+            License::CcBySa40 => "CC-BY-SA-4.0",
+            License::Gpl20 => "GPL-2.0",
+            License::OdbL10 => "ODbL-1.0",
+            License::CcByNcSa40 => "CC-BY-NC-SA-4.0",
+            License::Unknown => "unknown",
+            License::DbCl10 => "DbCL-1.0",
+            License::CcBySa30 => "CC-BY-SA-3.0",
+            License::CopyrightAuthors => "copyright-authors",
+            License::Other => "other",
+            License::RedditApi => "reddit-api",
+            License::WorldBank => "world-bank",
+            License::CcBy40 => "CC-BY-4.0",
+            License::CcByNc40 => "CC-BY-NC-4.0",
+            License::Pddl => "PDDL",
+            License::CcBy30 => "CC-BY-3.0",
+            License::CcBy30Igo => "CC-BY-3.0-IGO",
+            License::UsGovernmentWorks => "US-Government-Works",
+            License::CcByNcSa30Igo => "CC-BY-NC-SA-3.0-IGO",
+            License::CdlaPermissive10 => "CDLA-Permissive-1.0",
+            License::CdlaSharing10 => "CDLA-Sharing-1.0",
+            License::CcByNd40 => "CC-BY-ND-4.0",
+            License::CcByNcNd40 => "CC-BY-NC-ND-4.0",
+            License::OdcBy10 => "ODC-BY-1.0",
+            License::Lgpl30 => "LGPL-3.0",
+            License::Agpl30 => "AGPL-3.0",
+            License::Fdl13 => "FDL-1.3",
+            License::EuOdpLegalNotice => "EU-ODP-Legal-Notice",
+            License::Apache20 => "apache-2.0",
+            License::Gpl30 => "GPL-3.0",
+            License::Mit => "MIT",
+            License::Custom(name) => name,
+        }
+    }
+
+    /// Whether Kaggle accepts the license for the `target`.
+    pub fn is_accepted_for(&self, target: LicenseTarget) -> bool {
+        let names = match target {
+            LicenseTarget::Dataset => Self::DATASET_NAMES,
+            LicenseTarget::Kernel => Self::KERNEL_NAMES,
+            LicenseTarget::Model => Self::MODEL_NAMES,
+        };
+        names
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(self.name()))
+    }
+
+    /// Fail with a [`KaggleError::Metadata`] if Kaggle does not accept the
+    /// license for the `target`.
+    ///
+    /// Example
+    ///
+    /// ```
+    /// use kaggle::models::{License, LicenseTarget};
+    ///
+    /// let license: License = "MIT".parse().unwrap();
+    /// assert!(license.validate(LicenseTarget::Model).is_ok());
+    /// assert!(license.validate(LicenseTarget::Dataset).is_err());
+    /// ```
+    pub fn validate(&self, target: LicenseTarget) -> Result<(), KaggleError> {
+        if self.is_accepted_for(target) {
+            Ok(())
+        } else {
+            Err(KaggleError::meta(format!(
+                "license `{}` is not accepted for {}",
+                self, target
+            )))
+        }
+    }
 }
-// f.write_str(
+
 impl fmt::Display for License {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl fmt::Display for LicenseTarget {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            License::Cc010 => f.write_str("CC0-1.0"),
-            License::CcBySa40 => f.write_str("CC-BY-SA-4.0"),
-            License::Gpl20 => f.write_str("GPL-2.0"),
-            License::OdbL10 => f.write_str("ODbL-1.0"),
-            License::CcByNcSa40 => f.write_str("CC-BY-NC-SA-4.0"),
-            License::Unknown => f.write_str("unknown"),
-            License::DbCl10 => f.write_str("DbCL-1.0"),
-            License::CcBySa30 => f.write_str("CC-BY-SA-3.0"),
-            License::CopyrightAuthors => f.write_str("copyright-authors"),
-            License::Other => f.write_str("other"),
-            License::RedditApi => f.write_str("reddit-api"),
-            License::WorldBank => f.write_str("world-bank"),
+            LicenseTarget::Dataset => f.write_str("datasets"),
+            LicenseTarget::Kernel => f.write_str("kernels"),
+            LicenseTarget::Model => f.write_str("models"),
         }
     }
 }
@@ -52,21 +270,11 @@ impl fmt::Display for License {
 impl FromStr for License {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let license = match s {
-            "CC0-1.0" => License::Cc010,
-            "CC-BY-SA-4.0" => License::CcBySa40,
-            "GPL-2.0" => License::Gpl20,
-            "ODbL-1.0" => License::OdbL10,
-            "CC-BY-NC-SA-4.0" => License::CcByNcSa40,
-            "unknown" => License::Unknown,
-            "DbCL-1.0" => License::DbCl10,
-            "CC-BY-SA-3.0" => License::CcBySa30,
-            "copyright-authors" => License::CopyrightAuthors,
-            "other" => License::Other,
-            "reddit-api" => License::RedditApi,
-            "world-bank" => License::WorldBank,
-            _ => License::Other,
-        };
+        let license = Self::KNOWN
+            .iter()
+            .find(|license| license.name() == s)
+            .cloned()
+            .unwrap_or_else(|| License::Custom(s.to_string()));
         Ok(license)
     }
 }
@@ -90,7 +298,7 @@ impl Serialize for License {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("License", 13)?;
+        let mut state = serializer.serialize_struct("License", 1)?;
         state.serialize_field("name", &self.to_string())?;
         state.end()
     }
@@ -114,5 +322,32 @@ mod tests {
             serde_json::from_str::<License>(r#"{"name":"other"}"#).unwrap(),
             License::Other
         );
+        assert_eq!(
+            serde_json::from_str::<License>(r#"{"name":"CC-BY-4.0"}"#).unwrap(),
+            License::CcBy40
+        );
+    }
+
+    #[test]
+    fn custom_license_round_trip() {
+        let license: License = serde_json::from_str(r#"{"name":"Apache 2.0"}"#).unwrap();
+        assert_eq!(license, License::Custom("Apache 2.0".to_string()));
+        assert_eq!(
+            serde_json::to_string(&license).unwrap(),
+            r#"{"name":"Apache 2.0"}"#
+        );
+    }
+
+    #[test]
+    fn accepted_licenses() {
+        assert!(License::Cc010.is_accepted_for(LicenseTarget::Dataset));
+        assert!(!License::Cc010.is_accepted_for(LicenseTarget::Kernel));
+        assert!(License::Apache20.is_accepted_for(LicenseTarget::Kernel));
+        assert!(License::Mit.is_accepted_for(LicenseTarget::Model));
+        assert!(!License::Mit.is_accepted_for(LicenseTarget::Dataset));
+        assert!(License::Custom("Apache 2.0".to_string()).is_accepted_for(LicenseTarget::Model));
+        assert!(License::Custom("WTFPL".to_string())
+            .validate(LicenseTarget::Dataset)
+            .is_err());
     }
 }
@@ -1,72 +1,100 @@
 use crate::error::KaggleError;
-use crate::models::{Collaborator, DatasetColumn, DatasetUpdateSettingsRequest, License};
-use crate::query::{PushKernelType, PushLanguageType};
+use crate::filter::IgnoreFilter;
+use crate::models::extended::{DatasetMetadataInfo, KernelMetadata};
+use crate::models::{
+    Collaborator, DatasetColumn, DatasetUpdateSettingsRequest, KernelRef, License,
+};
+use crate::query::{Language, PushKernelType, PushLanguageType};
+use crate::upload;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::path::Path;
+use serde_json::{json, Value};
+use std::convert::TryInto;
+use std::fs;
+use std::path::{Path, PathBuf};
 
+/// The content of a `dataset-metadata.json` file.
+///
+/// Example
+///
+/// ```no_run
+/// use kaggle::models::metadata::DatasetMetadataFile;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let metadata: DatasetMetadataFile =
+///     serde_json::from_slice(&std::fs::read("dataset-metadata.json")?)?;
+/// println!("{}", metadata.id);
+/// # Ok(())
+/// # }
+/// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Metadata {
+pub struct DatasetMetadataFile {
     pub title: String,
-    #[serde(rename = "datasetId")]
+    /// The `owner/slug` identifier of the dataset
+    #[serde(alias = "datasetId")]
     pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none", default, alias = "idNo")]
+    pub id_no: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub subtitle: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub description: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub id_no: Option<i32>,
-    #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub code_file: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub language: Option<PushLanguageType>,
-    #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub kernel_type: Option<PushKernelType>,
     /// Whether or not the dataset should be private
-    #[serde(skip_serializing_if = "Option::is_none", default)]
+    #[serde(skip_serializing_if = "Option::is_none", default, alias = "isPrivate")]
     pub is_private: Option<bool>,
-    #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub enable_gpu: Option<bool>,
-    #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub enable_internet: Option<bool>,
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub collaborators: Vec<Collaborator>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub licenses: Vec<License>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub resources: Vec<Resource>,
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub keywords: Vec<String>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub kernel_sources: Vec<String>,
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub dataset_sources: Vec<String>,
+    pub collaborators: Vec<Collaborator>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
-    pub competition_sources: Vec<String>,
+    pub resources: Vec<Resource>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub data: Option<serde_json::Value>,
 }
 
-impl Metadata {
-    pub fn is_valid_kernel_string(s: impl AsRef<str>) -> Result<(), KaggleError> {
-        let s = s.as_ref();
-        let mut split = s.split('/');
-        if let Some(kernel_slug) = split.nth(1) {
-            if kernel_slug.len() < 5 {
-                Err(KaggleError::meta(format!(
-                    "Kernel slug `{}` must be at least five characters.",
-                    kernel_slug
-                )))
-            } else {
-                Ok(())
-            }
-        } else {
-            Err(KaggleError::meta(format!(
-                "Invalid kernel source identifier. expected form `{{username}}/{{identifier-slug}}`, but got {}",
-                s
-            ),
-            ))
-        }
+impl DatasetMetadataFile {
+    /// The name of the metadata file in a dataset folder.
+    pub const FILE_NAME: &'static str = "dataset-metadata.json";
+
+    /// Placeholder for the dataset slug of a new template.
+    pub const SLUG_PLACEHOLDER: &'static str = "INSERT_SLUG_HERE";
+
+    /// Placeholder for the title of a new template.
+    pub const TITLE_PLACEHOLDER: &'static str = "INSERT_TITLE_HERE";
+
+    /// Write a starter `dataset-metadata.json` for the dataset in `folder`
+    /// and return its path.
+    ///
+    /// The template is owned by `username` and lists every file of the folder
+    /// that is not ignored as resource. An existing metadata file is only
+    /// replaced if `force` is set.
+    ///
+    /// Example
+    ///
+    /// ```no_run
+    /// use kaggle::models::metadata::DatasetMetadataFile;
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let file = DatasetMetadataFile::init(".", "me", false)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn init(folder: impl AsRef<Path>, username: &str, force: bool) -> anyhow::Result<PathBuf> {
+        let folder = folder.as_ref();
+        let resources = IgnoreFilter::new(folder, &[] as &[&str])?
+            .files()?
+            .iter()
+            .filter_map(|f| upload::relative_name(f))
+            .map(|path| json!({ "path": path }))
+            .collect::<Vec<_>>();
+        let template = json!({
+            "title": Self::TITLE_PLACEHOLDER,
+            "id": format!("{}/{}", username, Self::SLUG_PLACEHOLDER),
+            "licenses": [License::Cc010],
+            "resources": resources,
+        });
+        write_template(folder.join(Self::FILE_NAME), &template, force)
     }
 
     pub fn owner_slug(&self) -> Option<&str> {
@@ -77,52 +105,195 @@ impl Metadata {
         self.id.split('/').nth(1)
     }
 
-    pub fn is_dataset_sources_valid(&self) -> Result<(), KaggleError> {
-        for s in &self.dataset_sources {
-            if s.split('/').count() < 2 {
-                return Err(KaggleError::meta(format!(
-                    "Invalid dataset source identifier. expected form `{{username}}/{{identifier-slug}}`, but got {}",
-                    s
-                ),
-                ));
-            }
+    /// The descriptions and columns of the resources in the shape of the
+    /// `data` of a metadata update, `None` if there are no resources.
+    pub fn resource_data(&self) -> Option<Value> {
+        if self.resources.is_empty() {
+            return None;
         }
-        Ok(())
+        let data = self
+            .resources
+            .iter()
+            .map(|resource| {
+                let columns = resource
+                    .schema
+                    .as_ref()
+                    .map(|schema| {
+                        schema
+                            .get_processed_columns()
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, col)| col.with_order(i as f32))
+                            .collect()
+                    })
+                    .unwrap_or_else(Vec::new);
+                json!({
+                    "name": resource.path,
+                    "description": resource.description,
+                    "columns": columns,
+                })
+            })
+            .collect();
+        Some(Value::Array(data))
     }
+}
 
-    pub fn is_kernel_sources_valid(&self) -> Result<(), KaggleError> {
-        for s in &self.kernel_sources {
-            Self::is_valid_kernel_string(s)?;
-        }
-        Ok(())
+/// The content of a `kernel-metadata.json` file.
+///
+/// The camelCase keys of the kernel metadata returned by the API, like
+/// `codeFile` or `datasetDataSources`, are accepted as aliases.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KernelMetadataFile {
+    /// The `owner/slug` identifier of the kernel
+    pub id: String,
+    /// The numeric id of an existing kernel, takes precedence over `id`
+    #[serde(skip_serializing_if = "Option::is_none", default, alias = "idNo")]
+    pub id_no: Option<i32>,
+    pub title: String,
+    /// The path of the notebook or script relative to the kernel folder
+    #[serde(skip_serializing_if = "Option::is_none", default, alias = "codeFile")]
+    pub code_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub language: Option<PushLanguageType>,
+    #[serde(skip_serializing_if = "Option::is_none", default, alias = "kernelType")]
+    pub kernel_type: Option<PushKernelType>,
+    #[serde(skip_serializing_if = "Option::is_none", default, alias = "isPrivate")]
+    pub is_private: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default, alias = "enableGpu")]
+    pub enable_gpu: Option<bool>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        default,
+        alias = "enableInternet"
+    )]
+    pub enable_internet: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default, alias = "categoryIds")]
+    pub keywords: Vec<String>,
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        default,
+        alias = "datasetDataSources"
+    )]
+    pub dataset_sources: Vec<String>,
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        default,
+        alias = "kernelDataSources"
+    )]
+    pub kernel_sources: Vec<String>,
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        default,
+        alias = "competitionDataSources"
+    )]
+    pub competition_sources: Vec<String>,
+}
+
+impl KernelMetadataFile {
+    /// The name of the metadata file in a kernel folder.
+    pub const FILE_NAME: &'static str = "kernel-metadata.json";
+
+    /// Placeholder for the kernel slug of a new template.
+    pub const SLUG_PLACEHOLDER: &'static str = "INSERT_KERNEL_SLUG_HERE";
+
+    /// Placeholder for the title of a new template.
+    pub const TITLE_PLACEHOLDER: &'static str = "INSERT_TITLE_HERE";
+
+    /// Placeholder for the code file of a new template.
+    pub const CODE_FILE_PLACEHOLDER: &'static str = "INSERT_CODE_FILE_PATH_HERE";
+
+    /// Write a starter `kernel-metadata.json` for the kernel in `folder` and
+    /// return its path.
+    ///
+    /// The template is owned by `username`. If the folder contains a notebook
+    /// or a script it is used as code file and determines the language and
+    /// kernel type. An existing metadata file is only replaced if `force` is
+    /// set.
+    pub fn init(folder: impl AsRef<Path>, username: &str, force: bool) -> anyhow::Result<PathBuf> {
+        let folder = folder.as_ref();
+        let mut files = fs::read_dir(folder)?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        files.sort();
+        let code = files.iter().filter(|f| f.is_file()).find_map(|f| {
+            let (language, kernel_type) = match f.extension()?.to_str()?.to_lowercase().as_str() {
+                "ipynb" => (PushLanguageType::Python, PushKernelType::Notebook),
+                "py" => (PushLanguageType::Python, PushKernelType::Script),
+                "r" => (PushLanguageType::R, PushKernelType::Script),
+                "rmd" => (PushLanguageType::Rmarkdown, PushKernelType::Script),
+                _ => return None,
+            };
+            Some((f.file_name()?.to_str()?, language, kernel_type))
+        });
+        let (code_file, language, kernel_type) = code.unwrap_or((
+            Self::CODE_FILE_PLACEHOLDER,
+            PushLanguageType::Python,
+            PushKernelType::Notebook,
+        ));
+        let template = json!({
+            "id": format!("{}/{}", username, Self::SLUG_PLACEHOLDER),
+            "title": Self::TITLE_PLACEHOLDER,
+            "code_file": code_file,
+            "language": language,
+            "kernel_type": kernel_type,
+            "is_private": true,
+            "enable_gpu": false,
+            "enable_internet": true,
+            "dataset_sources": [],
+            "competition_sources": [],
+            "kernel_sources": [],
+        });
+        write_template(folder.join(Self::FILE_NAME), &template, force)
     }
 
-    /// Validate resources is a wrapper to validate the existence of files and
-    /// that there are no duplicates for a folder and set of resources.
-    pub fn validate_resource(&self, root: impl AsRef<Path>) -> Result<(), KaggleError> {
-        let root = root.as_ref();
-        let mut unique = HashSet::with_capacity(self.resources.len());
-        for resource in &self.resources {
-            let file = root.join(&resource.path);
-            if !file.exists() {
-                return Err(KaggleError::FileNotFound(file));
-            }
-            if !unique.insert(&resource.path) {
-                return Err(KaggleError::Metadata {
-                    msg: format!(
-                        "path {} was specified more than once in the metadata",
-                        resource.path
-                    ),
-                });
-            }
+    /// Create the metadata file of a pulled kernel whose source is stored as
+    /// `code_file`.
+    pub fn from_remote(metadata: KernelMetadata, code_file: impl ToString) -> Self {
+        let language = match metadata.language {
+            Some(Language::Python) => Some(PushLanguageType::Python),
+            Some(Language::R) => Some(PushLanguageType::R),
+            Some(Language::Rmarkdown) => Some(PushLanguageType::Rmarkdown),
+            _ => None,
+        };
+        Self {
+            id: metadata.ref_,
+            id_no: metadata.id.try_into().ok(),
+            title: metadata.title,
+            code_file: Some(code_file.to_string()),
+            language,
+            kernel_type: metadata.kernel_type,
+            is_private: metadata.is_private,
+            enable_gpu: metadata.enable_gpu,
+            enable_internet: metadata.enable_internet,
+            keywords: metadata.category_ids,
+            dataset_sources: metadata.dataset_data_sources,
+            kernel_sources: metadata.kernel_data_sources,
+            competition_sources: metadata.competition_data_sources,
         }
+    }
+
+    /// Check that `s` is a kernel reference of the form
+    /// `{username}/{identifier-slug}`, see [`KernelRef`].
+    pub fn is_valid_kernel_string(s: impl AsRef<str>) -> Result<(), KaggleError> {
+        s.as_ref().parse::<KernelRef>().map(|_| ())
+    }
+}
 
-        Ok(())
+fn write_template(path: PathBuf, template: &Value, force: bool) -> anyhow::Result<PathBuf> {
+    if path.exists() && !force {
+        return Err(KaggleError::meta(format!(
+            "{} already exists, use force to overwrite it",
+            path.display()
+        ))
+        .into());
     }
+    fs::write(&path, serde_json::to_string_pretty(template)?)?;
+    Ok(path)
 }
 
-impl From<Metadata> for DatasetUpdateSettingsRequest {
-    fn from(val: Metadata) -> Self {
+impl From<DatasetMetadataFile> for DatasetUpdateSettingsRequest {
+    fn from(val: DatasetMetadataFile) -> Self {
+        let data = val.data.clone().or_else(|| val.resource_data());
         let mut settings = DatasetUpdateSettingsRequest::with_title(val.title)
             .with_licenses(val.licenses)
             .with_keywords(val.keywords)
@@ -133,7 +304,7 @@ impl From<Metadata> for DatasetUpdateSettingsRequest {
         if let Some(p) = val.is_private {
             settings.set_is_private(p);
         }
-        if let Some(d) = val.data {
+        if let Some(d) = data {
             settings.set_data(d);
         }
         if let Some(desc) = val.description {
@@ -143,6 +314,60 @@ impl From<Metadata> for DatasetUpdateSettingsRequest {
     }
 }
 
+impl From<DatasetMetadataInfo> for DatasetMetadataFile {
+    /// Convert the remote metadata into the shape of a
+    /// `dataset-metadata.json`, the file descriptions and columns become the
+    /// resources.
+    ///
+    /// The `id` is only the dataset slug if the metadata names no owner,
+    /// like for datasets of organizations.
+    fn from(info: DatasetMetadataInfo) -> Self {
+        let id = match info.owner_user.as_str().filter(|owner| !owner.is_empty()) {
+            Some(owner) => format!("{}/{}", owner, info.dataset_slug),
+            None => info.dataset_slug.clone(),
+        };
+        let resources = info
+            .data
+            .into_iter()
+            .map(|data| {
+                let fields: Vec<_> = data
+                    .columns
+                    .iter()
+                    .filter_map(|col| {
+                        Some(Field {
+                            name: col.name()?.clone(),
+                            description: col.description().cloned(),
+                            type_field: col.original_type().or_else(|| col.get_type()).cloned(),
+                        })
+                    })
+                    .collect();
+                Resource {
+                    path: data.name,
+                    description: data.description,
+                    schema: if fields.is_empty() {
+                        None
+                    } else {
+                        Some(Schema { fields })
+                    },
+                }
+            })
+            .collect();
+        Self {
+            title: info.title,
+            id,
+            id_no: info.dataset_id.try_into().ok(),
+            subtitle: Some(info.subtitle).filter(|s| !s.is_empty()),
+            description: Some(info.description).filter(|s| !s.is_empty()),
+            is_private: Some(info.is_private),
+            licenses: info.licenses,
+            keywords: info.keywords,
+            collaborators: info.collaborators,
+            resources,
+            data: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Resource {
     pub path: String,
@@ -151,6 +376,15 @@ pub struct Resource {
     pub schema: Option<Schema>,
 }
 
+impl Resource {
+    /// The path of the resource relative to the dataset folder, without a
+    /// leading `./` or trailing `/`.
+    pub fn relative_path(&self) -> &Path {
+        let path = self.path.trim_end_matches('/');
+        Path::new(path.strip_prefix("./").unwrap_or(path))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Schema {
     pub fields: Vec<Field>,
@@ -181,7 +415,7 @@ impl Schema {
 
                 if str_types.contains(&ty.as_str()) {
                     col.set_type("string".to_string());
-                } else if ty == "numeric" || ty == "number" || ty == "year" {
+                } else if ["numeric", "number", "integer", "year"].contains(&ty.as_str()) {
                     col.set_type("numeric".to_string());
                 } else if ty == "boolean" {
                     col.set_type("boolean".to_string());
@@ -209,3 +443,110 @@ pub struct Field {
     #[serde(rename = "type", skip_serializing_if = "Option::is_none", default)]
     pub type_field: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn init_templates() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("images")).unwrap();
+        fs::write(root.join("data.csv"), "a,b\n").unwrap();
+        fs::write(root.join("images/cat.jpg"), "").unwrap();
+        fs::write(root.join("analysis.ipynb"), "{}").unwrap();
+
+        let file = DatasetMetadataFile::init(root, "me", false).unwrap();
+        let meta: DatasetMetadataFile = serde_json::from_slice(&fs::read(&file).unwrap()).unwrap();
+        assert_eq!(meta.id, "me/INSERT_SLUG_HERE");
+        let paths: Vec<_> = meta.resources.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, vec!["analysis.ipynb", "data.csv", "images/cat.jpg"]);
+        assert!(DatasetMetadataFile::init(root, "me", false).is_err());
+        assert!(DatasetMetadataFile::init(root, "me", true).is_ok());
+
+        let file = KernelMetadataFile::init(root, "me", false).unwrap();
+        let meta: KernelMetadataFile = serde_json::from_slice(&fs::read(&file).unwrap()).unwrap();
+        assert_eq!(meta.code_file.as_deref(), Some("analysis.ipynb"));
+        assert_eq!(meta.kernel_type, Some(PushKernelType::Notebook));
+    }
+
+    #[test]
+    fn legacy_keys() {
+        let dataset: DatasetMetadataFile = serde_json::from_value(json!({
+            "title": "Weather stations",
+            "datasetId": "me/weather-stations",
+        }))
+        .unwrap();
+        assert_eq!(dataset.id, "me/weather-stations");
+        assert_eq!(
+            serde_json::to_value(&dataset).unwrap()["id"],
+            "me/weather-stations"
+        );
+
+        let kernel: KernelMetadataFile = serde_json::from_value(json!({
+            "id": "me/my-analysis",
+            "title": "My analysis",
+            "codeFile": "analysis.py",
+            "kernelType": "script",
+            "enableGpu": true,
+            "datasetDataSources": ["me/weather-stations"],
+        }))
+        .unwrap();
+        assert_eq!(kernel.code_file.as_deref(), Some("analysis.py"));
+        assert_eq!(kernel.kernel_type, Some(PushKernelType::Script));
+        assert_eq!(kernel.enable_gpu, Some(true));
+        assert_eq!(kernel.dataset_sources, vec!["me/weather-stations"]);
+    }
+
+    #[test]
+    fn remote_metadata_round_trip() {
+        let info: DatasetMetadataInfo = serde_json::from_value(json!({
+            "datasetId": 1346,
+            "datasetSlug": "weather-stations",
+            "ownerUser": "me",
+            "usabilityRating": 1.0,
+            "totalViews": 0,
+            "totalVotes": 0,
+            "totalDownloads": 0,
+            "title": "Weather stations",
+            "subtitle": "Hourly readings of weather stations",
+            "description": "Readings",
+            "isPrivate": false,
+            "licenses": [{"name": "CC0-1.0"}],
+            "keywords": ["weather"],
+            "collaborators": [],
+            "data": [{
+                "name": "readings.csv",
+                "description": "All readings",
+                "totalBytes": 10,
+                "columns": [
+                    {"order": 0.0, "name": "station", "type": "string", "originalType": "string", "description": "The station"},
+                    {"order": 1.0, "name": "temp", "type": "numeric", "originalType": "integer", "description": null}
+                ]
+            }]
+        }))
+        .unwrap();
+        let file = DatasetMetadataFile::from(info);
+        assert_eq!(file.id, "me/weather-stations");
+        assert_eq!(file.id_no, Some(1346));
+        let fields = &file.resources[0].schema.as_ref().unwrap().fields;
+        assert_eq!(fields[1].type_field.as_deref(), Some("integer"));
+
+        let json = serde_json::to_string(&file).unwrap();
+        let file: DatasetMetadataFile = serde_json::from_str(&json).unwrap();
+        let settings = serde_json::to_value(DatasetUpdateSettingsRequest::from(file)).unwrap();
+        assert_eq!(
+            settings["data"],
+            json!([{
+                "name": "readings.csv",
+                "description": "All readings",
+                "columns": [
+                    {"order": 0.0, "name": "station", "type": "string", "originalType": "string", "description": "The station"},
+                    {"order": 1.0, "name": "temp", "type": "numeric", "originalType": "integer", "description": null}
+                ]
+            }])
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::models::dataset_version_plan::FieldChange;
+use crate::models::extended::DatasetMetadataInfo;
+use crate::models::metadata::DatasetMetadataFile;
+use crate::models::DatasetUpdateSettingsRequest;
+
+/// The differences between a local `dataset-metadata.json` and the settings
+/// of the remote dataset, created by
+/// [`KaggleApiClient::dataset_metadata_diff`].
+///
+/// Fields that the local file omits are reported as well, because a full
+/// metadata update would clear them on the remote dataset.
+///
+/// [`KaggleApiClient::dataset_metadata_diff`]: crate::KaggleApiClient::dataset_metadata_diff
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataDiff {
+    /// The changed fields, file and column descriptions are named like
+    /// `resources[data.csv].columns[id].description`
+    pub changes: Vec<FieldChange>,
+}
+
+impl MetadataDiff {
+    /// Compare the `local` metadata with the `remote` dataset field by field.
+    ///
+    /// File and column descriptions that the local file omits are not
+    /// compared, they keep their remote value.
+    pub fn new(local: &DatasetMetadataFile, remote: &DatasetMetadataInfo) -> Self {
+        let mut changes = Vec::new();
+        if local.title != remote.title {
+            changes.push(FieldChange::new("title", &remote.title, &local.title));
+        }
+        let subtitle = local.subtitle.as_deref().unwrap_or_default();
+        if subtitle != remote.subtitle {
+            changes.push(FieldChange::new("subtitle", &remote.subtitle, subtitle));
+        }
+        let description = local.description.as_deref().unwrap_or_default();
+        if description != remote.description {
+            changes.push(FieldChange::new(
+                "description",
+                &remote.description,
+                description,
+            ));
+        }
+        if sorted(&local.licenses, |l| l.to_string()) != sorted(&remote.licenses, |l| l.to_string())
+        {
+            changes.push(FieldChange::new(
+                "licenses",
+                &remote.licenses,
+                &local.licenses,
+            ));
+        }
+        if sorted(&local.keywords, String::clone) != sorted(&remote.keywords, String::clone) {
+            changes.push(FieldChange::new(
+                "keywords",
+                &remote.keywords,
+                &local.keywords,
+            ));
+        }
+        let collaborator = |c: &crate::models::Collaborator| {
+            (c.username().to_lowercase(), c.role().to_lowercase())
+        };
+        if sorted(&local.collaborators, collaborator) != sorted(&remote.collaborators, collaborator)
+        {
+            changes.push(FieldChange::new(
+                "collaborators",
+                &remote.collaborators,
+                &local.collaborators,
+            ));
+        }
+
+        let remote_files: HashMap<_, _> = remote
+            .data
+            .iter()
+            .map(|data| (data.name.as_str(), data))
+            .collect();
+        for resource in &local.resources {
+            let name = resource.relative_path().to_string_lossy();
+            let remote_file = remote_files.get(name.as_ref());
+            let remote_description = remote_file.and_then(|f| f.description.as_ref());
+            if resource.description.is_some() && resource.description.as_ref() != remote_description
+            {
+                changes.push(FieldChange::new(
+                    &format!("resources[{}].description", name),
+                    &remote_description,
+                    &resource.description,
+                ));
+            }
+            let fields = resource.schema.iter().flat_map(|s| s.fields.iter());
+            for field in fields {
+                let remote_description = remote_file
+                    .and_then(|f| f.columns.iter().find(|col| col.name() == Some(&field.name)))
+                    .and_then(|col| col.description());
+                if field.description.is_some() && field.description.as_ref() != remote_description {
+                    changes.push(FieldChange::new(
+                        &format!("resources[{}].columns[{}].description", name, field.name),
+                        &remote_description,
+                        &field.description,
+                    ));
+                }
+            }
+        }
+        Self { changes }
+    }
+
+    /// Whether the local metadata matches the remote dataset.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Whether the field with the name `field` changed.
+    pub fn is_changed(&self, field: &str) -> bool {
+        self.changes.iter().any(|c| c.field == field)
+    }
+
+    /// A metadata update that only contains the changed fields of `local`.
+    ///
+    /// The `data` is the metadata of all files, so it is sent in full if any
+    /// file or column description changed. Descriptions that the local file
+    /// omits are left out instead of being sent as `null`.
+    pub fn update_request(&self, local: &DatasetMetadataFile) -> DatasetUpdateSettingsRequest {
+        let mut settings = DatasetUpdateSettingsRequest::default();
+        if self.is_changed("title") {
+            settings.set_title(local.title.clone());
+        }
+        if self.is_changed("subtitle") {
+            settings.set_subtitle(local.subtitle.clone().unwrap_or_default());
+        }
+        if self.is_changed("description") {
+            settings.set_description(local.description.clone().unwrap_or_default());
+        }
+        if self.is_changed("licenses") {
+            settings.set_licenses(local.licenses.clone());
+        }
+        if self.is_changed("keywords") {
+            settings.set_keywords(local.keywords.clone());
+        }
+        if self.is_changed("collaborators") {
+            settings.set_collaborators(local.collaborators.clone());
+        }
+
+        if self
+            .changes
+            .iter()
+            .any(|c| c.field.starts_with("resources["))
+        {
+            if let Some(mut data) = local.resource_data() {
+                remove_null_descriptions(&mut data);
+                settings.set_data(data);
+            }
+        }
+        settings
+    }
+}
+
+/// Remove the `description` keys without a value of the files and their
+/// columns.
+fn remove_null_descriptions(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Array(items) => items.iter_mut().for_each(remove_null_descriptions),
+        serde_json::Value::Object(obj) => {
+            if obj.get("description").is_some_and(|d| d.is_null()) {
+                obj.remove("description");
+            }
+            if let Some(columns) = obj.get_mut("columns") {
+                remove_null_descriptions(columns);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn sorted<T, K: Ord>(items: &[T], key: impl Fn(&T) -> K) -> Vec<K> {
+    let mut keys: Vec<_> = items.iter().map(key).collect();
+    keys.sort();
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::metadata::Resource;
+    use serde_json::json;
+
+    fn remote() -> DatasetMetadataInfo {
+        serde_json::from_value(json!({
+            "datasetId": 1,
+            "datasetSlug": "weather-stations",
+            "ownerUser": "me",
+            "usabilityRating": 1.0,
+            "totalViews": 0,
+            "totalVotes": 0,
+            "totalDownloads": 0,
+            "title": "Weather stations",
+            "subtitle": "",
+            "description": "Readings",
+            "isPrivate": false,
+            "licenses": [{"name": "CC0-1.0"}],
+            "keywords": ["weather", "climate"],
+            "collaborators": [{"username": "you", "role": "writer"}],
+            "data": [{
+                "name": "readings.csv",
+                "description": "All readings",
+                "totalBytes": 10,
+                "columns": [{"name": "station", "description": "The station"}]
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn unchanged() {
+        let local = DatasetMetadataFile::from(remote());
+        assert!(MetadataDiff::new(&local, &remote()).is_empty());
+    }
+
+    #[test]
+    fn changed_fields() {
+        let mut local = DatasetMetadataFile::from(remote());
+        local.keywords = vec!["climate".to_string(), "weather".to_string()];
+        local.collaborators.clear();
+        local.description = Some("Hourly readings".to_string());
+        local.resources[0].schema.as_mut().unwrap().fields[0].description =
+            Some("The station id".to_string());
+        local.resources.push(Resource {
+            path: "stations.csv".to_string(),
+            description: None,
+            schema: None,
+        });
+
+        let diff = MetadataDiff::new(&local, &remote());
+        let fields: Vec<_> = diff.changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "description",
+                "collaborators",
+                "resources[readings.csv].columns[station].description"
+            ]
+        );
+
+        let settings = serde_json::to_value(diff.update_request(&local)).unwrap();
+        assert_eq!(
+            settings,
+            json!({
+                "description": "Hourly readings",
+                "collaborators": [],
+                "data": [{
+                    "name": "readings.csv",
+                    "description": "All readings",
+                    "columns": [{
+                        "order": 0.0,
+                        "name": "station",
+                        "type": null,
+                        "originalType": null,
+                        "description": "The station id"
+                    }]
+                }, {
+                    "name": "stations.csv",
+                    "columns": []
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn omitted_descriptions() {
+        let mut local = DatasetMetadataFile::from(remote());
+        local.resources[0].description = None;
+        local.resources[0].schema.as_mut().unwrap().fields[0].description = None;
+        assert!(MetadataDiff::new(&local, &remote()).is_empty());
+    }
+}
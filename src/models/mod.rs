@@ -2,20 +2,35 @@ pub use self::collaborator::Collaborator;
 pub use self::dataset_column::DatasetColumn;
 pub use self::dataset_new_request::{DatasetNew, DatasetNewRequest};
 pub use self::dataset_new_version_request::DatasetNewVersionRequest;
+pub use self::dataset_status::DatasetStatus;
 pub use self::dataset_update_settings_request::DatasetUpdateSettingsRequest;
 pub use self::dataset_upload_file::DatasetUploadFile;
+pub use self::dataset_version_plan::{DatasetVersionPlan, FieldChange, PlannedFile};
 pub use self::error::Error;
+pub use self::kernel_log::{KernelLog, LogEntry, LogStream, Traceback};
 pub use self::kernel_push_request::KernelPushRequest;
-pub use self::license::License;
+pub use self::kernel_status::KernelStatus;
+pub use self::license::{License, LicenseTarget};
+pub use self::metadata_diff::MetadataDiff;
+pub use self::refs::{CompetitionRef, DatasetRef, IntoRef, KernelRef};
+pub use self::validation::{Diagnostic, MetadataKind, Severity, ValidationReport};
 
 mod collaborator;
+pub mod datapackage;
 mod dataset_column;
 mod dataset_new_request;
 mod dataset_new_version_request;
+mod dataset_status;
 mod dataset_update_settings_request;
 mod dataset_upload_file;
+mod dataset_version_plan;
 mod error;
 pub mod extended;
+mod kernel_log;
 mod kernel_push_request;
+mod kernel_status;
 mod license;
 pub mod metadata;
+mod metadata_diff;
+mod refs;
+mod validation;
@@ -0,0 +1,452 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::KaggleError;
+
+/// A reference to a dataset, like `owner/slug` or `owner/slug/versions/3`.
+///
+/// Full kaggle.com URLs, like
+/// `https://www.kaggle.com/datasets/owner/slug/versions/3`, are accepted as
+/// well.
+///
+/// Example
+///
+/// ```
+/// use kaggle::models::DatasetRef;
+///
+/// let dataset: DatasetRef = "https://www.kaggle.com/datasets/uciml/iris/versions/2"
+///     .parse()
+///     .unwrap();
+/// assert_eq!(dataset.owner(), "uciml");
+/// assert_eq!(dataset.slug(), "iris");
+/// assert_eq!(dataset.version_number(), Some(2));
+/// assert_eq!(dataset.to_string(), "uciml/iris/versions/2");
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DatasetRef {
+    owner: String,
+    slug: String,
+    version: Option<u32>,
+}
+
+/// A reference to a kernel, like `owner/slug` or `owner/slug/versions/3`.
+///
+/// Full kaggle.com URLs, like `https://www.kaggle.com/code/owner/slug`, are
+/// accepted as well.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct KernelRef {
+    owner: String,
+    slug: String,
+    version: Option<u32>,
+}
+
+/// A reference to a competition by its slug, like `titanic`.
+///
+/// Full kaggle.com URLs, like `https://www.kaggle.com/competitions/titanic`,
+/// are accepted as well.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct CompetitionRef {
+    slug: String,
+}
+
+/// Conversion of identifiers into references.
+///
+/// Implemented for strings and the reference types themselves, so client
+/// methods accept both. Identifiers without an owner, like a plain `slug`,
+/// belong to `default_owner`.
+pub trait IntoRef<R> {
+    fn into_ref(self, default_owner: &str) -> Result<R, KaggleError>;
+}
+
+impl DatasetRef {
+    /// The maximum length of a dataset slug.
+    pub const MAX_SLUG_LEN: usize = 50;
+
+    /// The minimum length of the slug of a new dataset, older datasets may
+    /// have shorter slugs.
+    pub const MIN_NEW_SLUG_LEN: usize = 6;
+
+    pub fn new(owner: impl ToString, slug: impl ToString) -> Result<Self, KaggleError> {
+        let (owner, slug) = (owner.to_string(), slug.to_string());
+        check_owner("dataset", &owner)?;
+        check_slug("dataset", &slug, 1, Self::MAX_SLUG_LEN)?;
+        Ok(Self {
+            owner,
+            slug,
+            version: None,
+        })
+    }
+
+    /// Set the version of the dataset.
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    pub fn slug(&self) -> &str {
+        &self.slug
+    }
+
+    pub fn version_number(&self) -> Option<u32> {
+        self.version
+    }
+
+    /// Check that the slug is long enough for a new dataset.
+    pub fn validate_new(&self) -> Result<(), KaggleError> {
+        check_slug(
+            "dataset",
+            &self.slug,
+            Self::MIN_NEW_SLUG_LEN,
+            Self::MAX_SLUG_LEN,
+        )
+    }
+
+    fn parse(s: &str, default_owner: Option<&str>) -> Result<Self, KaggleError> {
+        let (owner, slug, version) = parse_owned("dataset", s, &["datasets"], default_owner)?;
+        let dataset = Self::new(owner, slug)?;
+        Ok(Self { version, ..dataset })
+    }
+}
+
+impl KernelRef {
+    /// The maximum length of a kernel slug.
+    pub const MAX_SLUG_LEN: usize = 50;
+
+    /// The minimum length of a kernel slug.
+    pub const MIN_SLUG_LEN: usize = 5;
+
+    pub fn new(owner: impl ToString, slug: impl ToString) -> Result<Self, KaggleError> {
+        let (owner, slug) = (owner.to_string(), slug.to_string());
+        check_owner("kernel", &owner)?;
+        check_slug("kernel", &slug, Self::MIN_SLUG_LEN, Self::MAX_SLUG_LEN)?;
+        Ok(Self {
+            owner,
+            slug,
+            version: None,
+        })
+    }
+
+    /// Set the version of the kernel.
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    pub fn slug(&self) -> &str {
+        &self.slug
+    }
+
+    pub fn version_number(&self) -> Option<u32> {
+        self.version
+    }
+
+    fn parse(s: &str, default_owner: Option<&str>) -> Result<Self, KaggleError> {
+        let (owner, slug, version) = parse_owned("kernel", s, &["code", "kernels"], default_owner)?;
+        let kernel = Self::new(owner, slug)?;
+        Ok(Self { version, ..kernel })
+    }
+}
+
+impl CompetitionRef {
+    /// The maximum length of a competition slug.
+    pub const MAX_SLUG_LEN: usize = 50;
+
+    pub fn new(slug: impl ToString) -> Result<Self, KaggleError> {
+        let slug = slug.to_string();
+        check_slug("competition", &slug, 1, Self::MAX_SLUG_LEN)?;
+        Ok(Self { slug })
+    }
+
+    pub fn slug(&self) -> &str {
+        &self.slug
+    }
+}
+
+impl FromStr for DatasetRef {
+    type Err = KaggleError;
+
+    /// Parse a reference that includes the owner.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, None)
+    }
+}
+
+impl FromStr for KernelRef {
+    type Err = KaggleError;
+
+    /// Parse a reference that includes the owner.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, None)
+    }
+}
+
+impl FromStr for CompetitionRef {
+    type Err = KaggleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let segments = url_segments(s, &["competitions", "c"])?;
+        match segments.as_slice() {
+            [slug] => Self::new(slug),
+            [slug, ..] if is_url(s) => Self::new(slug),
+            _ => Err(invalid("competition", s, "`{identifier-slug}`")),
+        }
+    }
+}
+
+impl fmt::Display for DatasetRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.owner, self.slug)?;
+        if let Some(version) = self.version {
+            write!(f, "/versions/{}", version)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for KernelRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.owner, self.slug)?;
+        if let Some(version) = self.version {
+            write!(f, "/versions/{}", version)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CompetitionRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.slug)
+    }
+}
+
+macro_rules! impl_ref {
+    ($($ty:ty => $parse:expr),*) => {
+        $(
+            impl IntoRef<$ty> for $ty {
+                fn into_ref(self, _: &str) -> Result<$ty, KaggleError> {
+                    Ok(self)
+                }
+            }
+
+            impl IntoRef<$ty> for &$ty {
+                fn into_ref(self, _: &str) -> Result<$ty, KaggleError> {
+                    Ok(self.clone())
+                }
+            }
+
+            impl IntoRef<$ty> for &str {
+                fn into_ref(self, default_owner: &str) -> Result<$ty, KaggleError> {
+                    $parse(self, default_owner)
+                }
+            }
+
+            impl IntoRef<$ty> for String {
+                fn into_ref(self, default_owner: &str) -> Result<$ty, KaggleError> {
+                    $parse(&self, default_owner)
+                }
+            }
+
+            impl IntoRef<$ty> for &String {
+                fn into_ref(self, default_owner: &str) -> Result<$ty, KaggleError> {
+                    $parse(self, default_owner)
+                }
+            }
+
+            impl Serialize for $ty {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $ty {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let s = String::deserialize(deserializer)?;
+                    s.parse().map_err(serde::de::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+impl_ref!(
+    DatasetRef => |s, owner| DatasetRef::parse(s, Some(owner)),
+    KernelRef => |s, owner| KernelRef::parse(s, Some(owner)),
+    CompetitionRef => |s: &str, _| s.parse()
+);
+
+fn is_url(s: &str) -> bool {
+    s.starts_with("https://") || s.starts_with("http://")
+}
+
+/// The path segments of an identifier, a kaggle.com URL is stripped to the
+/// path after one of the `prefixes`.
+fn url_segments<'a>(s: &'a str, prefixes: &[&str]) -> Result<Vec<&'a str>, KaggleError> {
+    let path = if is_url(s) {
+        let rest = s
+            .split_once("://")
+            .map(|(_, rest)| rest)
+            .unwrap_or_default();
+        let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        if host != "kaggle.com" && !host.ends_with(".kaggle.com") {
+            return Err(KaggleError::meta(format!(
+                "`{}` is not a kaggle.com URL",
+                s
+            )));
+        }
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let path = path.trim_start_matches('/');
+        prefixes
+            .iter()
+            .find_map(|prefix| path.strip_prefix(prefix)?.strip_prefix('/'))
+            .unwrap_or(path)
+    } else {
+        s
+    };
+    Ok(path.trim_end_matches('/').split('/').collect())
+}
+
+/// Split an `owner/slug[/versions/n]` identifier.
+fn parse_owned(
+    kind: &str,
+    s: &str,
+    prefixes: &[&str],
+    default_owner: Option<&str>,
+) -> Result<(String, String, Option<u32>), KaggleError> {
+    let expected = "`{username}/{identifier-slug}`";
+    let segments = url_segments(s, prefixes)?;
+    let (owner, slug, rest) = match (segments.as_slice(), default_owner) {
+        ([slug], Some(owner)) => (owner, *slug, &[][..]),
+        ([owner, slug, rest @ ..], _) => (*owner, *slug, rest),
+        _ => return Err(invalid(kind, s, expected)),
+    };
+    let version = match rest {
+        [] => None,
+        ["versions", version] => Some(
+            version
+                .parse()
+                .map_err(|_| invalid(kind, s, "a numeric version"))?,
+        ),
+        // tabs of the kaggle.com page, like `/data` or `/code`
+        [_] if is_url(s) => None,
+        _ => return Err(invalid(kind, s, expected)),
+    };
+    Ok((owner.to_string(), slug.to_string(), version))
+}
+
+fn invalid(kind: &str, s: &str, expected: &str) -> KaggleError {
+    KaggleError::meta(format!(
+        "Invalid {} identifier. expected form {}, but got `{}`",
+        kind, expected, s
+    ))
+}
+
+fn is_valid_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+fn check_owner(kind: &str, owner: &str) -> Result<(), KaggleError> {
+    if owner.is_empty() || !owner.chars().all(is_valid_char) {
+        return Err(KaggleError::meta(format!(
+            "Invalid owner `{}` of {}, only letters, digits, `-` and `_` are allowed",
+            owner, kind
+        )));
+    }
+    Ok(())
+}
+
+fn check_slug(kind: &str, slug: &str, min: usize, max: usize) -> Result<(), KaggleError> {
+    if slug.is_empty() || !slug.chars().all(is_valid_char) {
+        return Err(KaggleError::meta(format!(
+            "Invalid {} slug `{}`, only letters, digits, `-` and `_` are allowed",
+            kind, slug
+        )));
+    }
+    if slug.len() < min || slug.len() > max {
+        return Err(KaggleError::meta(format!(
+            "The {} slug `{}` must be between {} and {} characters",
+            kind, slug, min, max
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dataset() {
+        let dataset: DatasetRef = "uciml/iris".parse().unwrap();
+        assert_eq!((dataset.owner(), dataset.slug()), ("uciml", "iris"));
+        assert_eq!(dataset.version_number(), None);
+
+        let dataset: DatasetRef = "uciml/iris/versions/3".parse().unwrap();
+        assert_eq!(dataset.version_number(), Some(3));
+        assert_eq!(dataset.to_string(), "uciml/iris/versions/3");
+
+        assert!("iris".parse::<DatasetRef>().is_err());
+        assert!("uciml/iris/versions/x".parse::<DatasetRef>().is_err());
+        assert!("uciml/iris/extra".parse::<DatasetRef>().is_err());
+        assert!("uci ml/iris".parse::<DatasetRef>().is_err());
+        assert!("uciml/iris"
+            .parse::<DatasetRef>()
+            .unwrap()
+            .validate_new()
+            .is_err());
+    }
+
+    #[test]
+    fn default_owner() {
+        let dataset: DatasetRef = "iris".into_ref("me").unwrap();
+        assert_eq!(dataset.to_string(), "me/iris");
+        let dataset: DatasetRef = "uciml/iris".into_ref("me").unwrap();
+        assert_eq!(dataset.owner(), "uciml");
+    }
+
+    #[test]
+    fn parse_urls() {
+        let dataset: DatasetRef = "https://www.kaggle.com/datasets/uciml/iris/data"
+            .parse()
+            .unwrap();
+        assert_eq!(dataset.to_string(), "uciml/iris");
+
+        let kernel: KernelRef = "https://www.kaggle.com/code/me/my-analysis?scriptVersionId=1"
+            .parse()
+            .unwrap();
+        assert_eq!(kernel.to_string(), "me/my-analysis");
+
+        let competition: CompetitionRef = "https://www.kaggle.com/c/titanic/leaderboard"
+            .parse()
+            .unwrap();
+        assert_eq!(competition.slug(), "titanic");
+
+        assert!("https://example.com/datasets/uciml/iris"
+            .parse::<DatasetRef>()
+            .is_err());
+    }
+
+    #[test]
+    fn kernel_slug_length() {
+        assert!("me/abcd".parse::<KernelRef>().is_err());
+        assert!("me/abcde".parse::<KernelRef>().is_ok());
+    }
+
+    #[test]
+    fn serde_as_string() {
+        let kernel: KernelRef = serde_json::from_str(r#""me/my-analysis""#).unwrap();
+        assert_eq!(
+            serde_json::to_string(&kernel).unwrap(),
+            r#""me/my-analysis""#
+        );
+        assert!(serde_json::from_str::<KernelRef>(r#""abc""#).is_err());
+    }
+}
@@ -0,0 +1,640 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Component, Path};
+
+use crate::error::KaggleError;
+use crate::models::metadata::{DatasetMetadataFile, KernelMetadataFile};
+use crate::models::{DatasetRef, LicenseTarget};
+use crate::query::{PushKernelType, PushLanguageType};
+
+/// What a dataset metadata file is validated for.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MetadataKind {
+    /// A `dataset-metadata.json` to create a new dataset
+    Dataset,
+    /// A `dataset-metadata.json` to create a new version of a dataset
+    DatasetVersion,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    /// The metadata is accepted, but probably not as intended
+    Warning,
+    /// The metadata is rejected
+    Error,
+}
+
+/// A single problem of a metadata file.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct Diagnostic {
+    /// The path of the field, like `resources[2].path`
+    pub field: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}: {}", severity, self.field, self.message)
+    }
+}
+
+/// All problems of a metadata file, see [`DatasetMetadataFile::validate`]
+/// and [`KernelMetadataFile::validate`].
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize)]
+pub struct ValidationReport {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    /// Whether there are no errors, warnings are allowed.
+    pub fn is_ok(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+    }
+
+    /// Fail with a [`KaggleError::Validation`] if there are any errors.
+    pub fn into_result(self) -> Result<(), KaggleError> {
+        if self.is_ok() {
+            Ok(())
+        } else {
+            Err(KaggleError::Validation { report: self })
+        }
+    }
+
+    fn error(&mut self, field: impl ToString, message: impl ToString) {
+        self.push(field, Severity::Error, message)
+    }
+
+    fn warning(&mut self, field: impl ToString, message: impl ToString) {
+        self.push(field, Severity::Warning, message)
+    }
+
+    fn push(&mut self, field: impl ToString, severity: Severity, message: impl ToString) {
+        self.diagnostics.push(Diagnostic {
+            field: field.to_string(),
+            severity,
+            message: message.to_string(),
+        });
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl DatasetMetadataFile {
+    /// Check the metadata for every problem that would make Kaggle, or this
+    /// client, reject it.
+    ///
+    /// Resources are resolved relative to `folder`.
+    ///
+    /// Example
+    ///
+    /// ```no_run
+    /// use kaggle::models::metadata::DatasetMetadataFile;
+    /// use kaggle::models::MetadataKind;
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let metadata: DatasetMetadataFile =
+    ///     serde_json::from_slice(&std::fs::read("dataset-metadata.json")?)?;
+    /// for diagnostic in &metadata.validate(MetadataKind::Dataset, ".").diagnostics {
+    ///     println!("{}", diagnostic);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate(&self, kind: MetadataKind, folder: impl AsRef<Path>) -> ValidationReport {
+        self.validate_in(kind, Some(folder.as_ref()))
+    }
+
+    /// Validate the metadata, resources are only checked if a `folder` is
+    /// set.
+    pub(crate) fn validate_in(
+        &self,
+        kind: MetadataKind,
+        folder: Option<&Path>,
+    ) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        match kind {
+            MetadataKind::Dataset => {
+                self.check_id(&mut report);
+                self.check_title(&mut report);
+                self.check_licenses(&mut report);
+            }
+            MetadataKind::DatasetVersion => {
+                if self.id_no.is_none() {
+                    check_slug(&mut report, &self.id, Self::SLUG_PLACEHOLDER);
+                }
+            }
+        }
+        self.check_subtitle(&mut report);
+        check_keywords(&mut report, &self.keywords);
+        if let Some(folder) = folder {
+            self.check_resources(&mut report, folder);
+        }
+        report
+    }
+
+    fn check_id(&self, report: &mut ValidationReport) {
+        if let Some(slug) = check_slug(report, &self.id, Self::SLUG_PLACEHOLDER) {
+            if slug.len() < DatasetRef::MIN_NEW_SLUG_LEN || slug.len() > DatasetRef::MAX_SLUG_LEN {
+                report.error(
+                    "id",
+                    format!(
+                        "the dataset slug must be between {} and {} characters",
+                        DatasetRef::MIN_NEW_SLUG_LEN,
+                        DatasetRef::MAX_SLUG_LEN
+                    ),
+                )
+            }
+        }
+    }
+
+    fn check_title(&self, report: &mut ValidationReport) {
+        if self.title == Self::TITLE_PLACEHOLDER || self.title == Self::SLUG_PLACEHOLDER {
+            report.error(
+                "title",
+                "default title detected, please change it before uploading",
+            );
+        } else if !(6..=50).contains(&self.title.chars().count()) {
+            report.error(
+                "title",
+                "the dataset title must be between 6 and 50 characters",
+            );
+        }
+    }
+
+    fn check_licenses(&self, report: &mut ValidationReport) {
+        if self.licenses.len() != 1 {
+            report.error(
+                "licenses",
+                format!(
+                    "exactly one license must be specified, but got {}",
+                    self.licenses.len()
+                ),
+            );
+        }
+        for (i, license) in self.licenses.iter().enumerate() {
+            if let Err(KaggleError::Metadata { msg }) = license.validate(LicenseTarget::Dataset) {
+                report.error(format!("licenses[{}]", i), msg);
+            }
+        }
+    }
+
+    fn check_subtitle(&self, report: &mut ValidationReport) {
+        if let Some(subtitle) = &self.subtitle {
+            let len = subtitle.chars().count();
+            if !(20..=80).contains(&len) {
+                report.error(
+                    "subtitle",
+                    format!(
+                        "the subtitle must be between 20 and 80 characters, but has {}",
+                        len
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_resources(&self, report: &mut ValidationReport, folder: &Path) {
+        let mut unique = HashSet::with_capacity(self.resources.len());
+        for (i, resource) in self.resources.iter().enumerate() {
+            let field = format!("resources[{}].path", i);
+            let path = resource.relative_path();
+            if path.as_os_str().is_empty()
+                || path
+                    .components()
+                    .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+            {
+                report.error(
+                    field,
+                    format!(
+                        "path `{}` must be relative to the dataset folder",
+                        resource.path
+                    ),
+                );
+                continue;
+            }
+            if !unique.insert(path) {
+                report.error(
+                    field,
+                    format!("path `{}` is specified more than once", resource.path),
+                );
+                continue;
+            }
+            if !folder.join(path).exists() {
+                report.error(
+                    field,
+                    format!("file `{}` not found", folder.join(path).display()),
+                );
+            }
+        }
+    }
+}
+
+impl KernelMetadataFile {
+    /// Check the metadata for every problem that would make Kaggle, or this
+    /// client, reject it.
+    ///
+    /// The code file is resolved relative to `folder`.
+    pub fn validate(&self, folder: impl AsRef<Path>) -> ValidationReport {
+        self.validate_in(Some(folder.as_ref()))
+    }
+
+    /// Validate the metadata, the code file is only checked if a `folder` is
+    /// set.
+    pub(crate) fn validate_in(&self, folder: Option<&Path>) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        self.check_id(&mut report);
+        self.check_title(&mut report);
+        self.check_code_file(&mut report, folder);
+        self.check_kernel_type(&mut report);
+        check_keywords(&mut report, &self.keywords);
+        self.check_sources(&mut report);
+        report
+    }
+
+    fn check_id(&self, report: &mut ValidationReport) {
+        if let Some(slug) = check_slug(report, &self.id, Self::SLUG_PLACEHOLDER) {
+            if slug.to_lowercase() != slug::slugify(&self.title) {
+                report.error(
+                    "id",
+                    format!(
+                        "the kernel slug `{}` does not match the slug of the title `{}`",
+                        slug,
+                        slug::slugify(&self.title)
+                    ),
+                )
+            }
+        }
+    }
+
+    fn check_title(&self, report: &mut ValidationReport) {
+        if self.title == Self::TITLE_PLACEHOLDER {
+            report.error(
+                "title",
+                "default title detected, please change it before uploading",
+            );
+        } else if self.title.chars().count() < 5 {
+            report.error("title", "the kernel title must be at least five characters")
+        }
+    }
+
+    fn check_code_file(&self, report: &mut ValidationReport, folder: Option<&Path>) {
+        match self.code_file.as_deref() {
+            None | Some(Self::CODE_FILE_PLACEHOLDER) | Some("") => report.error(
+                "code_file",
+                "a source file must be specified in the metadata",
+            ),
+            Some(code_file) => {
+                if let Some(folder) = folder {
+                    if !folder.join(code_file).is_file() {
+                        report.error(
+                            "code_file",
+                            format!(
+                                "source file `{}` not found",
+                                folder.join(code_file).display()
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_kernel_type(&self, report: &mut ValidationReport) {
+        if self.language.is_none() {
+            report.warning("language", "no language set, the server default is used");
+        }
+        if self.kernel_type.is_none() {
+            report.warning(
+                "kernel_type",
+                "no kernel type set, the server default is used",
+            );
+        }
+        if self.kernel_type == Some(PushKernelType::Notebook)
+            && self.language == Some(PushLanguageType::Rmarkdown)
+        {
+            report.warning("language", "rmarkdown notebooks are pushed as R notebooks");
+        }
+    }
+
+    fn check_sources(&self, report: &mut ValidationReport) {
+        for (i, source) in self.dataset_sources.iter().enumerate() {
+            if source.split('/').count() < 2 {
+                report.error(
+                    format!("dataset_sources[{}]", i),
+                    format!(
+                        "expected form `{{username}}/{{identifier-slug}}`, but got `{}`",
+                        source
+                    ),
+                );
+            }
+        }
+        for (i, source) in self.kernel_sources.iter().enumerate() {
+            if let Err(KaggleError::Metadata { msg }) = Self::is_valid_kernel_string(source) {
+                report.error(format!("kernel_sources[{}]", i), msg);
+            }
+        }
+        for (i, source) in self.competition_sources.iter().enumerate() {
+            if source.trim().is_empty() {
+                report.error(
+                    format!("competition_sources[{}]", i),
+                    "competition source must not be empty",
+                );
+            }
+        }
+    }
+}
+
+/// Check the form of an `owner/slug` identifier and return the slug if it is
+/// neither malformed nor the template `placeholder`.
+fn check_slug<'a>(
+    report: &mut ValidationReport,
+    id: &'a str,
+    placeholder: &str,
+) -> Option<&'a str> {
+    let parts: Vec<_> = id.split('/').collect();
+    let slug = match parts.as_slice() {
+        [slug] | [_, slug] if !slug.is_empty() && !parts[0].is_empty() => *slug,
+        _ => {
+            report.error(
+                "id",
+                format!(
+                    "expected form `{{username}}/{{identifier-slug}}`, but got `{}`",
+                    id
+                ),
+            );
+            return None;
+        }
+    };
+    if slug == placeholder {
+        report.error(
+            "id",
+            "default slug detected, please change it before uploading",
+        );
+        return None;
+    }
+    Some(slug)
+}
+
+fn check_keywords(report: &mut ValidationReport, keywords: &[String]) {
+    let mut unique = HashSet::with_capacity(keywords.len());
+    for (i, keyword) in keywords.iter().enumerate() {
+        if !unique.insert(keyword) {
+            report.warning(
+                format!("keywords[{}]", i),
+                format!("keyword `{}` is listed more than once", keyword),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::License;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn dataset() -> DatasetMetadataFile {
+        serde_json::from_value(serde_json::json!({
+            "title": "Weather stations",
+            "id": "me/weather-stations",
+            "licenses": [{"name": "CC0-1.0"}]
+        }))
+        .unwrap()
+    }
+
+    fn kernel() -> KernelMetadataFile {
+        serde_json::from_value(serde_json::json!({
+            "title": "My analysis",
+            "id": "me/my-analysis",
+            "code_file": "analysis.py",
+            "language": "python",
+            "kernel_type": "script"
+        }))
+        .unwrap()
+    }
+
+    fn fields(report: &ValidationReport) -> Vec<&str> {
+        report
+            .diagnostics
+            .iter()
+            .map(|d| d.field.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn valid_dataset() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(
+            dataset().validate(MetadataKind::Dataset, dir.path()),
+            ValidationReport::default()
+        );
+    }
+
+    #[test]
+    fn invalid_id() {
+        let mut meta = dataset();
+        meta.id = "a/b/c".to_string();
+        let report = meta.validate_in(MetadataKind::Dataset, None);
+        assert_eq!(fields(&report), vec!["id"]);
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn slug_placeholder() {
+        let mut meta = dataset();
+        meta.id = "me/INSERT_SLUG_HERE".to_string();
+        assert_eq!(
+            fields(&meta.validate_in(MetadataKind::DatasetVersion, None)),
+            vec!["id"]
+        );
+        meta.id_no = Some(1);
+        assert!(meta
+            .validate_in(MetadataKind::DatasetVersion, None)
+            .diagnostics
+            .is_empty());
+    }
+
+    #[test]
+    fn slug_length() {
+        let mut meta = dataset();
+        meta.id = "me/sixsix".to_string();
+        assert!(meta.validate_in(MetadataKind::Dataset, None).is_ok());
+        meta.id = "me/abcde".to_string();
+        assert_eq!(
+            fields(&meta.validate_in(MetadataKind::Dataset, None)),
+            vec!["id"]
+        );
+    }
+
+    #[test]
+    fn title_placeholder_and_length() {
+        let mut meta = dataset();
+        meta.title = DatasetMetadataFile::TITLE_PLACEHOLDER.to_string();
+        assert_eq!(
+            fields(&meta.validate_in(MetadataKind::Dataset, None)),
+            vec!["title"]
+        );
+        meta.title = "Short".to_string();
+        assert_eq!(
+            fields(&meta.validate_in(MetadataKind::Dataset, None)),
+            vec!["title"]
+        );
+    }
+
+    #[test]
+    fn licenses() {
+        let mut meta = dataset();
+        meta.licenses.push(License::Gpl20);
+        assert_eq!(
+            fields(&meta.validate_in(MetadataKind::Dataset, None)),
+            vec!["licenses"]
+        );
+        assert!(meta.validate_in(MetadataKind::DatasetVersion, None).is_ok());
+    }
+
+    #[test]
+    fn unaccepted_license() {
+        let mut meta = dataset();
+        meta.licenses = vec![License::Mit];
+        assert_eq!(
+            fields(&meta.validate_in(MetadataKind::Dataset, None)),
+            vec!["licenses[0]"]
+        );
+    }
+
+    #[test]
+    fn subtitle_length() {
+        let mut meta = dataset();
+        meta.subtitle = Some("too short".to_string());
+        assert_eq!(
+            fields(&meta.validate_in(MetadataKind::DatasetVersion, None)),
+            vec!["subtitle"]
+        );
+        meta.subtitle = Some("Hourly readings of weather stations".to_string());
+        assert!(meta.validate_in(MetadataKind::DatasetVersion, None).is_ok());
+    }
+
+    #[test]
+    fn duplicate_keywords() {
+        let mut meta = dataset();
+        meta.keywords = vec!["weather".to_string(), "weather".to_string()];
+        let report = meta.validate_in(MetadataKind::Dataset, None);
+        assert_eq!(fields(&report), vec!["keywords[1]"]);
+        assert!(report.is_ok());
+    }
+
+    #[test]
+    fn resources() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("data.csv"), "").unwrap();
+        let mut meta = dataset();
+        meta.resources = serde_json::from_value(serde_json::json!([
+            {"path": "data.csv"},
+            {"path": "./data.csv"},
+            {"path": "missing.csv"},
+            {"path": "../outside.csv"}
+        ]))
+        .unwrap();
+        let report = meta.validate(MetadataKind::Dataset, dir.path());
+        assert_eq!(
+            fields(&report),
+            vec![
+                "resources[1].path",
+                "resources[2].path",
+                "resources[3].path"
+            ]
+        );
+    }
+
+    #[test]
+    fn valid_kernel() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("analysis.py"), "").unwrap();
+        assert!(kernel().validate(dir.path()).diagnostics.is_empty());
+    }
+
+    #[test]
+    fn kernel_slug_matches_title() {
+        let mut meta = kernel();
+        meta.title = "Another analysis".to_string();
+        assert_eq!(fields(&meta.validate_in(None)), vec!["id"]);
+    }
+
+    #[test]
+    fn kernel_title_length() {
+        let mut meta = kernel();
+        meta.title = "abc".to_string();
+        meta.id = "me/abc".to_string();
+        assert_eq!(fields(&meta.validate_in(None)), vec!["title"]);
+    }
+
+    #[test]
+    fn code_file() {
+        let dir = TempDir::new().unwrap();
+        let mut meta = kernel();
+        assert_eq!(fields(&meta.validate(dir.path())), vec!["code_file"]);
+        meta.code_file = Some(KernelMetadataFile::CODE_FILE_PLACEHOLDER.to_string());
+        assert_eq!(fields(&meta.validate_in(None)), vec!["code_file"]);
+    }
+
+    #[test]
+    fn kernel_type_warnings() {
+        let mut meta = kernel();
+        meta.language = None;
+        meta.kernel_type = Some(PushKernelType::Notebook);
+        let report = meta.validate_in(None);
+        assert_eq!(fields(&report), vec!["language"]);
+        assert!(report.is_ok());
+        meta.language = Some(PushLanguageType::Rmarkdown);
+        let report = meta.validate_in(None);
+        assert_eq!(report.warnings().count(), 1);
+        assert!(report.warnings().all(|d| d.message.contains("R notebooks")));
+    }
+
+    #[test]
+    fn sources() {
+        let mut meta = kernel();
+        meta.dataset_sources = vec!["me/data".to_string(), "nodata".to_string()];
+        meta.kernel_sources = vec!["me/abc".to_string()];
+        meta.competition_sources = vec![" ".to_string()];
+        assert_eq!(
+            fields(&meta.validate_in(None)),
+            vec![
+                "dataset_sources[1]",
+                "kernel_sources[0]",
+                "competition_sources[0]"
+            ]
+        );
+    }
+}
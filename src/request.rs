@@ -4,7 +4,10 @@ use crate::query::{
     CompetitionCategory, CompetitionGroup, CompetitionSortBy, DatasetFileType, DatasetGroup,
     DatasetLicenseName, Group, KernelType, Language, OutputType, SortBy,
 };
+use ignore::overrides::{Override, OverrideBuilder};
+use std::fmt;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -315,6 +318,133 @@ impl KernelPullRequest {
     }
 }
 
+/// The progress of a single file download.
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadProgress<'a> {
+    /// The name of the downloaded file
+    pub file_name: &'a str,
+    /// The number of bytes written so far
+    pub downloaded: u64,
+    /// The size of the file, if known
+    pub total: Option<u64>,
+}
+
+/// A callback that receives the progress of downloads.
+pub type ProgressFn = Rc<dyn Fn(DownloadProgress<'_>)>;
+
+/// Which output files of a kernel are downloaded and how.
+///
+/// Example
+///
+/// ```no_run
+/// use kaggle::request::KernelOutputRequest;
+/// use kaggle::KaggleApiClient;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let kaggle = KaggleApiClient::builder().build()?;
+///     let files = kaggle
+///         .kernels_output(
+///             KernelOutputRequest::new("owner/kernel")
+///                 .pattern("*.csv")
+///                 .pattern("!sample_*.csv")
+///                 .progress(|p| println!("{}: {} bytes", p.file_name, p.downloaded)),
+///         )
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct KernelOutputRequest {
+    pub name: String,
+    pub output: Option<PathBuf>,
+    /// Glob patterns of the file names to download, patterns starting with
+    /// `!` exclude files. All files are downloaded if empty.
+    pub patterns: Vec<String>,
+    /// How many files are downloaded at the same time
+    pub concurrency: usize,
+    /// How many files are listed per request
+    pub page_size: Option<u32>,
+    /// Called whenever a chunk of a file was written
+    pub progress: Option<ProgressFn>,
+}
+
+impl KernelOutputRequest {
+    pub fn new(name: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            output: None,
+            patterns: Vec::new(),
+            concurrency: 4,
+            page_size: None,
+            progress: None,
+        }
+    }
+
+    pub fn output(mut self, output: impl AsRef<Path>) -> Self {
+        self.output = Some(output.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn patterns(mut self, patterns: Vec<String>) -> Self {
+        self.patterns = patterns;
+        self
+    }
+
+    pub fn pattern(mut self, pattern: impl ToString) -> Self {
+        self.patterns.push(pattern.to_string());
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    pub fn progress(mut self, progress: impl Fn(DownloadProgress<'_>) + 'static) -> Self {
+        self.progress = Some(Rc::new(progress));
+        self
+    }
+
+    /// Build the matcher for the `patterns`.
+    pub(crate) fn file_filter(&self) -> anyhow::Result<OutputFilter> {
+        let mut builder = OverrideBuilder::new("");
+        for pattern in &self.patterns {
+            builder.add(pattern)?;
+        }
+        Ok(OutputFilter(builder.build()?))
+    }
+}
+
+impl fmt::Debug for KernelOutputRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KernelOutputRequest")
+            .field("name", &self.name)
+            .field("output", &self.output)
+            .field("patterns", &self.patterns)
+            .field("concurrency", &self.concurrency)
+            .field("page_size", &self.page_size)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+/// Matches output file names against the patterns of a
+/// [`KernelOutputRequest`].
+pub(crate) struct OutputFilter(Override);
+
+impl OutputFilter {
+    /// Whether the file with this name should be downloaded.
+    pub(crate) fn is_match(&self, file_name: &str) -> bool {
+        !self.0.matched(file_name, false).is_ignore()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -329,4 +459,29 @@ mod tests {
         let x = Dummy { group: None };
         assert_eq!(r#"{"group":""}"#, serde_json::to_string(&x).unwrap());
     }
+
+    #[test]
+    fn output_patterns() {
+        let all = KernelOutputRequest::new("owner/kernel")
+            .file_filter()
+            .unwrap();
+        assert!(all.is_match("submission.csv"));
+
+        let filter = KernelOutputRequest::new("owner/kernel")
+            .pattern("*.csv")
+            .pattern("!sample_*")
+            .file_filter()
+            .unwrap();
+        assert!(filter.is_match("submission.csv"));
+        assert!(filter.is_match("results/scores.csv"));
+        assert!(!filter.is_match("sample_submission.csv"));
+        assert!(!filter.is_match("model.pt"));
+
+        let exclude = KernelOutputRequest::new("owner/kernel")
+            .pattern("!*.log")
+            .file_filter()
+            .unwrap();
+        assert!(exclude.is_match("model.pt"));
+        assert!(!exclude.is_match("kernel.log"));
+    }
 }
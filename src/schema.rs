@@ -0,0 +1,421 @@
+//! Infer [Frictionless table schemas] of CSV and TSV files for the resources
+//! of a dataset.
+//!
+//! [Frictionless table schemas]: https://specs.frictionlessdata.io/table-schema/
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::error::KaggleError;
+use crate::models::metadata::{DatasetMetadataFile, Field, Resource, Schema};
+
+/// Options that control how schemas are inferred.
+#[derive(Debug, Clone)]
+pub struct InferOptions {
+    /// How many data rows are sampled
+    pub sample_rows: usize,
+    /// The field delimiter, detected from the sample if `None`
+    pub delimiter: Option<u8>,
+    /// Whether the first row is a header, detected from the sample if `None`
+    pub has_header: Option<bool>,
+}
+
+impl Default for InferOptions {
+    fn default() -> Self {
+        Self {
+            sample_rows: 1000,
+            delimiter: None,
+            has_header: None,
+        }
+    }
+}
+
+impl InferOptions {
+    pub fn sample_rows(mut self, sample_rows: usize) -> Self {
+        self.sample_rows = sample_rows;
+        self
+    }
+
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    pub fn has_header(mut self, has_header: bool) -> Self {
+        self.has_header = Some(has_header);
+        self
+    }
+}
+
+/// The types that are inferred, in the order of precedence.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FieldType {
+    Integer,
+    Number,
+    Boolean,
+    Date,
+    Datetime,
+    Geopoint,
+    String,
+}
+
+impl FieldType {
+    const CANDIDATES: [FieldType; 6] = [
+        FieldType::Integer,
+        FieldType::Number,
+        FieldType::Boolean,
+        FieldType::Date,
+        FieldType::Datetime,
+        FieldType::Geopoint,
+    ];
+
+    /// The name of the type in a table schema.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldType::Integer => "integer",
+            FieldType::Number => "number",
+            FieldType::Boolean => "boolean",
+            FieldType::Date => "date",
+            FieldType::Datetime => "datetime",
+            FieldType::Geopoint => "geopoint",
+            FieldType::String => "string",
+        }
+    }
+
+    /// Whether the non-empty `value` is a valid value of this type.
+    pub fn matches(&self, value: &str) -> bool {
+        let value = value.trim();
+        match self {
+            FieldType::Integer => value.parse::<i64>().is_ok(),
+            FieldType::Number => value.parse::<f64>().is_ok_and(|n| n.is_finite()),
+            FieldType::Boolean => ["true", "false", "yes", "no"]
+                .iter()
+                .any(|b| value.eq_ignore_ascii_case(b)),
+            FieldType::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+            FieldType::Datetime => {
+                DateTime::parse_from_rfc3339(value).is_ok()
+                    || ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+                        .iter()
+                        .any(|f| NaiveDateTime::parse_from_str(value, f).is_ok())
+            }
+            FieldType::Geopoint => {
+                let mut parts = value.split(',').map(|p| p.trim().parse::<f64>());
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(Ok(lat)), Some(Ok(lon)), None) => {
+                        (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)
+                    }
+                    _ => false,
+                }
+            }
+            FieldType::String => true,
+        }
+    }
+
+    /// The most specific type that matches all non-empty `values`.
+    pub fn infer<'a>(values: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut candidates = Self::CANDIDATES.to_vec();
+        let mut empty = true;
+        for value in values.into_iter().filter(|v| !v.trim().is_empty()) {
+            empty = false;
+            candidates.retain(|c| c.matches(value));
+            if candidates.is_empty() {
+                break;
+            }
+        }
+        if empty {
+            return FieldType::String;
+        }
+        candidates.first().copied().unwrap_or(FieldType::String)
+    }
+}
+
+/// Whether the file is a delimited text file that schemas can be inferred
+/// for.
+pub fn is_tabular(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| ["csv", "tsv", "tab"].contains(&e.to_lowercase().as_str()))
+}
+
+/// Detect the delimiter of the `lines` of a file.
+///
+/// Picks the candidate out of `,`, `\t`, `;` and `|` that occurs the same
+/// number of times in every line and most often, `None` if no candidate does.
+pub fn detect_delimiter(lines: &[String]) -> Option<u8> {
+    let mut best = None;
+    for candidate in [b',', b'\t', b';', b'|'] {
+        let counts: Vec<_> = lines
+            .iter()
+            .filter(|l| !l.is_empty())
+            .map(|l| count_unquoted(l, candidate))
+            .collect();
+        let first = match counts.first() {
+            Some(first) if *first > 0 => *first,
+            _ => continue,
+        };
+        if counts.iter().all(|c| *c == first) && best.is_none_or(|(_, n)| first > n) {
+            best = Some((candidate, first));
+        }
+    }
+    best.map(|(d, _)| d)
+}
+
+fn count_unquoted(line: &str, delimiter: u8) -> usize {
+    let mut quoted = false;
+    let mut count = 0;
+    for b in line.bytes() {
+        if b == b'"' {
+            quoted = !quoted;
+        } else if b == delimiter && !quoted {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Infer the schema of the CSV or TSV file at `path` from a sample of its
+/// rows.
+///
+/// Example
+///
+/// ```no_run
+/// use kaggle::schema::{infer_schema, InferOptions};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let schema = infer_schema("train.csv", &InferOptions::default())?;
+/// for field in &schema.fields {
+///     println!("{}: {:?}", field.name, field.type_field);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn infer_schema(path: impl AsRef<Path>, options: &InferOptions) -> anyhow::Result<Schema> {
+    let path = path.as_ref();
+    let delimiter = match options.delimiter {
+        Some(delimiter) => delimiter,
+        None => {
+            let lines = BufReader::new(File::open(path)?)
+                .lines()
+                .take(10)
+                .collect::<Result<Vec<_>, _>>()?;
+            let is_tsv = path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("tsv"));
+            detect_delimiter(&lines).unwrap_or(if is_tsv { b'\t' } else { b',' })
+        }
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)?;
+    let mut rows = Vec::new();
+    for record in reader.records().take(options.sample_rows.saturating_add(1)) {
+        rows.push(record?.iter().map(str::to_string).collect::<Vec<_>>());
+    }
+    if rows.is_empty() {
+        return Err(KaggleError::meta(format!("{} is empty", path.display())).into());
+    }
+
+    let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+    let column = |rows: &[Vec<String>], i: usize| {
+        FieldType::infer(rows.iter().map(|r| r.get(i).map_or("", String::as_str)))
+    };
+
+    let has_header = options.has_header.unwrap_or_else(|| {
+        // the first row is data if it fits the types of the other rows
+        let (first, data) = rows.split_first().expect("not empty");
+        let typed: Vec<_> = (0..columns)
+            .map(|i| (i, column(data, i)))
+            .filter(|(_, ty)| *ty != FieldType::String)
+            .collect();
+        typed.is_empty()
+            || typed
+                .iter()
+                .any(|(i, ty)| !ty.matches(first.get(*i).map_or("", String::as_str)))
+    });
+
+    let (names, data) = if has_header {
+        let (first, data) = rows.split_first().expect("not empty");
+        (first.clone(), data)
+    } else {
+        (Vec::new(), &rows[..])
+    };
+    let data = &data[..data.len().min(options.sample_rows)];
+
+    let fields = (0..columns)
+        .map(|i| Field {
+            name: names
+                .get(i)
+                .filter(|n| !n.trim().is_empty())
+                .cloned()
+                .unwrap_or_else(|| format!("column_{}", i + 1)),
+            description: None,
+            type_field: Some(column(data, i).as_str().to_string()),
+        })
+        .collect();
+    Ok(Schema { fields })
+}
+
+impl Schema {
+    /// Keep the descriptions of fields of the `existing` schema with the same
+    /// name.
+    pub fn merge_descriptions(&mut self, existing: &Schema) {
+        let descriptions: HashMap<_, _> = existing
+            .fields
+            .iter()
+            .filter_map(|f| f.description.as_ref().map(|d| (f.name.as_str(), d)))
+            .collect();
+        for field in &mut self.fields {
+            if field.description.is_none() {
+                field.description = descriptions.get(field.name.as_str()).map(|d| d.to_string());
+            }
+        }
+    }
+}
+
+/// Infer the schemas of all tabular `resources` of the dataset `folder`.
+///
+/// Descriptions of existing schemas are kept. Returns how many schemas were
+/// inferred.
+pub fn infer_resource_schemas(
+    folder: impl AsRef<Path>,
+    resources: &mut [Resource],
+    options: &InferOptions,
+) -> anyhow::Result<usize> {
+    let folder = folder.as_ref();
+    let mut inferred = 0;
+    for resource in resources {
+        let path = folder.join(resource.relative_path());
+        if !path.is_file() || !is_tabular(&path) {
+            continue;
+        }
+        let mut schema = infer_schema(&path, options)?;
+        if let Some(existing) = &resource.schema {
+            schema.merge_descriptions(existing);
+        }
+        resource.schema = Some(schema);
+        inferred += 1;
+    }
+    Ok(inferred)
+}
+
+/// Infer the schemas of the tabular resources of the `dataset-metadata.json`
+/// in `folder` and update the file in place.
+///
+/// Only the `schema` of the resources is replaced, all other fields of the
+/// file and the descriptions of existing fields are kept. Returns the path of
+/// the metadata file.
+pub fn update_metadata_schemas(
+    folder: impl AsRef<Path>,
+    options: &InferOptions,
+) -> anyhow::Result<PathBuf> {
+    let folder = folder.as_ref();
+    let file = folder.join(DatasetMetadataFile::FILE_NAME);
+    if !file.exists() {
+        return Err(KaggleError::FileNotFound(file).into());
+    }
+    let mut metadata: Value = serde_json::from_slice(&fs::read(&file)?)?;
+
+    if let Some(resources) = metadata.get_mut("resources").and_then(Value::as_array_mut) {
+        for value in resources {
+            let mut resource: Resource = serde_json::from_value(value.clone())?;
+            if infer_resource_schemas(folder, std::slice::from_mut(&mut resource), options)? > 0 {
+                if let Some(obj) = value.as_object_mut() {
+                    obj.insert(
+                        "schema".to_string(),
+                        serde_json::to_value(&resource.schema)?,
+                    );
+                }
+            }
+        }
+    }
+
+    fs::write(&file, serde_json::to_string_pretty(&metadata)?)?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn types(schema: &Schema) -> Vec<(&str, &str)> {
+        schema
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.type_field.as_deref().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn infer_types() {
+        assert_eq!(FieldType::infer(vec!["1", "2", ""]), FieldType::Integer);
+        assert_eq!(FieldType::infer(vec!["1", "2.5"]), FieldType::Number);
+        assert_eq!(FieldType::infer(vec!["true", "No"]), FieldType::Boolean);
+        assert_eq!(FieldType::infer(vec!["2020-01-31"]), FieldType::Date);
+        assert_eq!(
+            FieldType::infer(vec!["2020-01-31 10:00:00", "2020-01-31T10:00:00Z"]),
+            FieldType::Datetime
+        );
+        assert_eq!(FieldType::infer(vec!["48.1, 11.5"]), FieldType::Geopoint);
+        assert_eq!(FieldType::infer(vec!["1", "a"]), FieldType::String);
+    }
+
+    #[test]
+    fn infer_csv_and_tsv() {
+        let dir = TempDir::new().unwrap();
+        let csv = dir.path().join("data.csv");
+        fs::write(
+            &csv,
+            "id,price,active,day,location\n1,2.5,true,2020-01-01,\"48.1, 11.5\"\n2,3,false,2020-01-02,\"1.0, 2.0\"\n",
+        )
+        .unwrap();
+        let schema = infer_schema(&csv, &InferOptions::default()).unwrap();
+        assert_eq!(
+            types(&schema),
+            vec![
+                ("id", "integer"),
+                ("price", "number"),
+                ("active", "boolean"),
+                ("day", "date"),
+                ("location", "geopoint"),
+            ]
+        );
+
+        let tsv = dir.path().join("data.tsv");
+        fs::write(&tsv, "1\tx\n2\ty\n").unwrap();
+        let schema = infer_schema(&tsv, &InferOptions::default()).unwrap();
+        assert_eq!(
+            types(&schema),
+            vec![("column_1", "integer"), ("column_2", "string")]
+        );
+    }
+
+    #[test]
+    fn update_keeps_descriptions() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("data.csv"), "id,name\n1,a\n").unwrap();
+        fs::write(
+            dir.path().join(DatasetMetadataFile::FILE_NAME),
+            r#"{"title": "My data", "id": "me/data", "resources": [{"path": "data.csv", "description": "rows", "schema": {"fields": [{"name": "id", "description": "The id"}]}}]}"#,
+        )
+        .unwrap();
+
+        let file = update_metadata_schemas(dir.path(), &InferOptions::default()).unwrap();
+        let metadata: DatasetMetadataFile =
+            serde_json::from_slice(&fs::read(file).unwrap()).unwrap();
+        let resource = &metadata.resources[0];
+        assert_eq!(resource.description.as_deref(), Some("rows"));
+        let fields = &resource.schema.as_ref().unwrap().fields;
+        assert_eq!(fields[0].description.as_deref(), Some("The id"));
+        assert_eq!(fields[0].type_field.as_deref(), Some("integer"));
+        assert_eq!(fields[1].type_field.as_deref(), Some("string"));
+    }
+}
@@ -0,0 +1,382 @@
+use crate::archive::{ArchiveMode, ArchiveOptions};
+use crate::filter::IgnoreFilter;
+use crate::models::metadata::Resource;
+use crate::models::DatasetUploadFile;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+pub use self::resumable::{ResumableOptions, UploadSession};
+pub use self::source::UploadSource;
+
+pub(crate) mod resumable;
+mod source;
+
+/// Which files of a dataset folder are uploaded.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum UploadMode {
+    /// Upload every file and directory of the folder that is not ignored.
+    /// Matching `resources` of the metadata only add descriptions and
+    /// schemas.
+    #[default]
+    Folder,
+    /// Only upload the paths that are listed in the `resources` of the
+    /// metadata.
+    Resources,
+    /// Upload every file of the folder and its subdirectories that is not
+    /// ignored under its path relative to the folder, like
+    /// `images/train/cat/001.jpg`. Directories are never archived.
+    Tree,
+}
+
+/// Options that control how the files of a dataset folder are uploaded.
+#[derive(Debug, Clone)]
+pub struct UploadOptions {
+    /// Which files of the folder are uploaded
+    pub mode: UploadMode,
+    /// How to archive directories beforehand
+    pub archive_mode: ArchiveMode,
+    /// The compression to use when directories are archived
+    pub archive_options: ArchiveOptions,
+    /// Additional gitignore-style patterns of files to skip, applied after the
+    /// patterns of the folder's `.kaggleignore` file
+    pub ignore_patterns: Vec<String>,
+    /// How many files are uploaded at the same time
+    pub concurrency: usize,
+    /// How often a failed upload of a single file is retried
+    pub retries: usize,
+    /// The delay before the first retry, doubled for every further retry
+    pub retry_delay: Duration,
+    /// Files that were already uploaded by a previous call, by their full
+    /// path, see [`UploadOptions::resume`]
+    pub completed: HashMap<PathBuf, DatasetUploadFile>,
+    /// Upload large files in chunks to a resumable upload session, if `None`
+    /// every file is uploaded with a single request
+    pub resumable: Option<ResumableOptions>,
+    /// Uploads of archived directories by the SHA-256 digest of the archive,
+    /// see [`UploadOptions::known_archive`]
+    pub known_archives: HashMap<String, DatasetUploadFile>,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            mode: Default::default(),
+            archive_mode: Default::default(),
+            archive_options: Default::default(),
+            ignore_patterns: Vec::new(),
+            concurrency: 4,
+            retries: 2,
+            retry_delay: Duration::from_secs(1),
+            completed: HashMap::new(),
+            resumable: None,
+            known_archives: HashMap::new(),
+        }
+    }
+}
+
+impl UploadOptions {
+    pub fn mode(mut self, mode: UploadMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn archive_mode(mut self, archive_mode: ArchiveMode) -> Self {
+        self.archive_mode = archive_mode;
+        self
+    }
+
+    pub fn archive_options(mut self, archive_options: ArchiveOptions) -> Self {
+        self.archive_options = archive_options;
+        self
+    }
+
+    pub fn ignore_patterns(mut self, ignore_patterns: Vec<String>) -> Self {
+        self.ignore_patterns = ignore_patterns;
+        self
+    }
+
+    pub fn ignore_pattern(mut self, ignore_pattern: impl ToString) -> Self {
+        self.ignore_patterns.push(ignore_pattern.to_string());
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    pub fn resumable(mut self, resumable: ResumableOptions) -> Self {
+        self.resumable = Some(resumable);
+        self
+    }
+
+    /// Reuse `upload` instead of uploading an archived directory again if the
+    /// archive's [`sha256_digest`] is `digest`.
+    ///
+    /// This only skips unchanged directories if the archives are built with
+    /// [`ArchiveOptions::deterministic`].
+    ///
+    /// [`sha256_digest`]: crate::archive::sha256_digest
+    pub fn known_archive(mut self, digest: impl ToString, upload: DatasetUploadFile) -> Self {
+        self.known_archives.insert(digest.to_string(), upload);
+        self
+    }
+
+    /// Reuse the tokens of the files that were successfully uploaded in a
+    /// previous, partially failed upload, so that only the failed files are
+    /// uploaded again.
+    ///
+    /// Example
+    ///
+    /// ```no_run
+    /// use kaggle::archive::ArchiveMode;
+    /// use kaggle::upload::UploadOptions;
+    /// use kaggle::{KaggleApiClient, KaggleError};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let kaggle = KaggleApiClient::builder().build()?;
+    ///     let options = UploadOptions::default().concurrency(8);
+    ///     let resp = match kaggle
+    ///         .dataset_create_version(".", "new version", true, false, options.clone())
+    ///         .await
+    ///     {
+    ///         Err(err) => match err.downcast::<KaggleError>()? {
+    ///             KaggleError::PartialUpload { report } => {
+    ///                 kaggle
+    ///                     .dataset_create_version(".", "new version", true, false, options.resume(report))
+    ///                     .await?
+    ///             }
+    ///             err => return Err(err.into()),
+    ///         },
+    ///         Ok(resp) => resp,
+    ///     };
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn resume(mut self, report: UploadReport) -> Self {
+        self.completed.extend(report.uploaded);
+        self
+    }
+
+    /// The filter that decides which files of the `folder` are skipped.
+    pub fn ignore_filter(&self, folder: impl AsRef<Path>) -> anyhow::Result<IgnoreFilter> {
+        IgnoreFilter::new(folder, &self.ignore_patterns)
+    }
+
+    /// The files of the `folder` that would be uploaded, relative to the
+    /// `folder` and sorted by path.
+    ///
+    /// Files inside directories are listed individually if the directories
+    /// are archived, otherwise directories are skipped.
+    pub fn preview(
+        &self,
+        folder: impl AsRef<Path>,
+        resources: &[Resource],
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let folder = folder.as_ref();
+        let filter = self.ignore_filter(folder)?;
+        let mut included = None;
+        let mut files = Vec::with_capacity(resources.len());
+        for entry in self.entries(folder, resources, &filter)? {
+            let path = entry.path.strip_prefix(folder)?;
+            if entry.path.is_file() {
+                files.push(path.to_path_buf());
+            } else {
+                if included.is_none() {
+                    included = Some(filter.files()?);
+                }
+                files.extend(
+                    included
+                        .iter()
+                        .flatten()
+                        .filter(|f| f.starts_with(path))
+                        .cloned(),
+                );
+            }
+        }
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    /// The files and directories of the `folder` to upload, according to the
+    /// mode, together with the resources that describe them.
+    pub(crate) fn entries<'a>(
+        &self,
+        folder: &Path,
+        resources: &'a [Resource],
+        filter: &IgnoreFilter,
+    ) -> anyhow::Result<Vec<UploadEntry<'a>>> {
+        let described: HashMap<_, _> = resources
+            .iter()
+            .map(|r| (folder.join(r.relative_path()), r))
+            .collect();
+
+        let paths = match self.mode {
+            UploadMode::Folder => {
+                let mut paths = fs::read_dir(folder)?
+                    .map(|e| e.map(|e| e.path()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                paths.sort();
+                paths
+            }
+            UploadMode::Resources => resources
+                .iter()
+                .map(|r| folder.join(r.relative_path()))
+                .collect(),
+            UploadMode::Tree => filter.files()?.iter().map(|f| folder.join(f)).collect(),
+        };
+
+        let mut seen = HashSet::with_capacity(paths.len());
+        let mut entries = Vec::with_capacity(paths.len());
+        for path in paths {
+            let is_dir = path.is_dir();
+            if !path.exists()
+                || filter.is_ignored(&path, is_dir)
+                || (is_dir && self.archive_mode == ArchiveMode::Skip)
+                || !seen.insert(path.clone())
+            {
+                continue;
+            }
+            let name = match self.mode {
+                UploadMode::Tree => relative_name(path.strip_prefix(folder)?),
+                _ => path
+                    .file_name()
+                    .and_then(|s| s.to_str())
+                    .map(str::to_string),
+            }
+            .ok_or_else(|| anyhow::anyhow!("Invalid file name {}", path.display()))?;
+            entries.push(UploadEntry {
+                resource: described.get(&path).copied(),
+                name,
+                path,
+            });
+        }
+        Ok(entries)
+    }
+}
+
+/// The outcome of an upload where at least one file failed to upload.
+#[derive(Debug, Default)]
+pub struct UploadReport {
+    /// The files that were uploaded successfully, by their full path
+    pub uploaded: Vec<(PathBuf, DatasetUploadFile)>,
+    /// The files that failed to upload after all retries, with the last error
+    pub failed: Vec<(PathBuf, anyhow::Error)>,
+}
+
+/// A file or directory of a dataset folder that is uploaded.
+#[derive(Debug, Clone)]
+pub(crate) struct UploadEntry<'a> {
+    /// The full path of the file or directory
+    pub path: PathBuf,
+    /// The name of the uploaded file, for directories the name before the
+    /// archive's extension is added
+    pub name: String,
+    /// The resource of the metadata that describes the entry
+    pub resource: Option<&'a Resource>,
+}
+
+/// The `/` separated form of a relative path.
+pub(crate) fn relative_name(path: &Path) -> Option<String> {
+    let parts = path
+        .components()
+        .map(|c| match c {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(parts.join("/"))
+}
+
+impl From<ArchiveMode> for UploadOptions {
+    fn from(archive_mode: ArchiveMode) -> Self {
+        UploadOptions::default().archive_mode(archive_mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn resource(path: &str) -> Resource {
+        Resource {
+            path: path.to_string(),
+            description: Some(format!("{} description", path)),
+            schema: None,
+        }
+    }
+
+    #[test]
+    fn folder_and_resources_mode() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        for file in &["dataset-metadata.json", "a.csv", "b.csv", "images/cat.jpg"] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let resources = vec![resource("./b.csv"), resource("images/")];
+
+        let options = UploadOptions::default();
+        let filter = options.ignore_filter(root).unwrap();
+        let entries = options.entries(root, &resources, &filter).unwrap();
+        let uploads: Vec<_> = entries
+            .iter()
+            .map(|e| (e.path.strip_prefix(root).unwrap(), e.resource.is_some()))
+            .collect();
+        assert_eq!(
+            uploads,
+            vec![(Path::new("a.csv"), false), (Path::new("b.csv"), true)]
+        );
+
+        let options = UploadOptions::from(ArchiveMode::Zip).mode(UploadMode::Resources);
+        assert_eq!(
+            options.preview(root, &resources).unwrap(),
+            vec![PathBuf::from("b.csv"), PathBuf::from("images/cat.jpg")]
+        );
+    }
+
+    #[test]
+    fn tree_mode() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        for file in &[
+            "dataset-metadata.json",
+            "a.csv",
+            "images/train/cat/001.jpg",
+            "images/.DS_Store",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let resources = vec![resource("./images/train/cat/001.jpg")];
+
+        let options = UploadOptions::from(ArchiveMode::Zip).mode(UploadMode::Tree);
+        let filter = options.ignore_filter(root).unwrap();
+        let entries = options.entries(root, &resources, &filter).unwrap();
+        let uploads: Vec<_> = entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.resource.is_some()))
+            .collect();
+        assert_eq!(
+            uploads,
+            vec![("a.csv", false), ("images/train/cat/001.jpg", true)]
+        );
+    }
+}
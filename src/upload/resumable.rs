@@ -0,0 +1,249 @@
+use reqwest::header::{HeaderMap, RANGE};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Options for uploading large files in chunks to a resumable upload session.
+///
+/// The session of every upload is persisted in the `state_dir`, so that an
+/// interrupted upload, even of a restarted process, continues with the bytes
+/// that were not committed yet instead of starting from the beginning.
+#[derive(Debug, Clone)]
+pub struct ResumableOptions {
+    /// The size of a single chunk, rounded up to a multiple of 256 KiB
+    pub chunk_size: usize,
+    /// Files smaller than this are uploaded with a single request
+    pub min_file_size: u64,
+    /// Where the state of the upload sessions is stored
+    pub state_dir: PathBuf,
+}
+
+impl ResumableOptions {
+    /// Chunks must be a multiple of this size, except for the last one
+    pub const CHUNK_ALIGNMENT: usize = 256 * 1024;
+
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn min_file_size(mut self, min_file_size: u64) -> Self {
+        self.min_file_size = min_file_size;
+        self
+    }
+
+    pub fn state_dir(mut self, state_dir: impl AsRef<Path>) -> Self {
+        self.state_dir = state_dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// The chunk size rounded up to the next multiple of
+    /// [`ResumableOptions::CHUNK_ALIGNMENT`].
+    pub(crate) fn aligned_chunk_size(&self) -> usize {
+        let chunks = self.chunk_size.max(1).div_ceil(Self::CHUNK_ALIGNMENT);
+        chunks * Self::CHUNK_ALIGNMENT
+    }
+
+    /// Whether a file of this size should be uploaded in chunks.
+    pub(crate) fn is_chunked(&self, content_length: u64) -> bool {
+        content_length > 0 && content_length >= self.min_file_size
+    }
+}
+
+impl Default for ResumableOptions {
+    fn default() -> Self {
+        let state_dir = std::env::var_os("KAGGLE_CONFIG_DIR")
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|p| p.join(".kaggle")))
+            .unwrap_or_else(std::env::temp_dir)
+            .join("uploads");
+        Self {
+            chunk_size: 32 * 1024 * 1024,
+            min_file_size: 64 * 1024 * 1024,
+            state_dir,
+        }
+    }
+}
+
+/// The persisted state of a resumable upload of a single file.
+///
+/// The session is started with a `POST` to the signed `create_url` of a
+/// [`FileUploadInfo`](crate::models::extended::FileUploadInfo), chunks are
+/// sent to the session uri returned in the response's `Location` header.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadSession {
+    /// The file that is uploaded
+    pub path: PathBuf,
+    /// The size of the file when the upload was started
+    pub total_bytes: u64,
+    /// The token that references the upload once it is complete
+    pub token: String,
+    /// The uri of the upload session that receives the chunks
+    pub session_url: String,
+}
+
+impl UploadSession {
+    /// The location of the state of the session for `file` in `state_dir`.
+    ///
+    /// The location depends on the file's path, size and modification time,
+    /// so a modified file never continues the upload of its previous content.
+    pub fn state_file(state_dir: impl AsRef<Path>, file: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let file = file.as_ref();
+        let meta = file.metadata()?;
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        let mut hasher = Sha256::new();
+        hasher.update(file.canonicalize()?.to_string_lossy().as_bytes());
+        hasher.update(meta.len().to_le_bytes());
+        hasher.update(modified.to_le_bytes());
+        Ok(Self::key_file(state_dir, hasher))
+    }
+
+    /// The location of the state of the session for the archive of the
+    /// directory `source` with the sha256 `digest` in `state_dir`.
+    ///
+    /// Archives are created in a new temporary directory for every upload, so
+    /// the location depends on the archived directory and the archive's
+    /// content instead of the archive's path.
+    pub fn archive_state_file(
+        state_dir: impl AsRef<Path>,
+        source: impl AsRef<Path>,
+        digest: &str,
+    ) -> io::Result<PathBuf> {
+        let mut hasher = Sha256::new();
+        hasher.update(source.as_ref().canonicalize()?.to_string_lossy().as_bytes());
+        hasher.update(digest.as_bytes());
+        Ok(Self::key_file(state_dir, hasher))
+    }
+
+    fn key_file(state_dir: impl AsRef<Path>, hasher: Sha256) -> PathBuf {
+        let key: String = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        state_dir.as_ref().join(format!("{}.json", key))
+    }
+
+    /// Read a persisted session, `None` if there is none.
+    pub fn load(state_file: impl AsRef<Path>) -> anyhow::Result<Option<Self>> {
+        match fs::read(state_file) {
+            Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Persist the session.
+    pub fn save(&self, state_file: impl AsRef<Path>) -> anyhow::Result<()> {
+        let state_file = state_file.as_ref();
+        if let Some(dir) = state_file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(state_file, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+}
+
+/// The state of an upload session as reported by the server.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum SessionStatus {
+    /// All bytes were received
+    Complete,
+    /// The number of bytes that were committed so far
+    Incomplete(u64),
+}
+
+/// The number of committed bytes from the `Range: bytes=0-<last>` header of an
+/// incomplete upload, no header means nothing was committed yet.
+pub(crate) fn committed_bytes(headers: &HeaderMap) -> anyhow::Result<u64> {
+    let range = match headers.get(RANGE) {
+        Some(range) => range.to_str()?,
+        None => return Ok(0),
+    };
+    let last = range
+        .strip_prefix("bytes=")
+        .and_then(|r| r.split('-').nth(1))
+        .ok_or_else(|| anyhow::anyhow!("Invalid range header `{}`", range))?;
+    Ok(last.trim().parse::<u64>()? + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parse_committed_bytes() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(committed_bytes(&headers).unwrap(), 0);
+        headers.insert(RANGE, HeaderValue::from_static("bytes=0-262143"));
+        assert_eq!(committed_bytes(&headers).unwrap(), 262144);
+        headers.insert(RANGE, HeaderValue::from_static("items=1"));
+        assert!(committed_bytes(&headers).is_err());
+    }
+
+    #[test]
+    fn aligned_chunks() {
+        let options = ResumableOptions::default().chunk_size(1);
+        assert_eq!(
+            options.aligned_chunk_size(),
+            ResumableOptions::CHUNK_ALIGNMENT
+        );
+        let options = options.chunk_size(ResumableOptions::CHUNK_ALIGNMENT * 2 + 1);
+        assert_eq!(
+            options.aligned_chunk_size(),
+            ResumableOptions::CHUNK_ALIGNMENT * 3
+        );
+    }
+
+    #[test]
+    fn persist_session() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("data.csv");
+        fs::write(&file, "a,b\n").unwrap();
+
+        let state_file = UploadSession::state_file(dir.path().join("state"), &file).unwrap();
+        assert_eq!(
+            state_file,
+            UploadSession::state_file(dir.path().join("state"), &file).unwrap()
+        );
+        assert!(UploadSession::load(&state_file).unwrap().is_none());
+
+        let session = UploadSession {
+            path: file,
+            total_bytes: 4,
+            token: "token".to_string(),
+            session_url: "https://storage.googleapis.com/upload?upload_id=1".to_string(),
+        };
+        session.save(&state_file).unwrap();
+        let loaded = UploadSession::load(&state_file).unwrap().unwrap();
+        assert_eq!(loaded.token, session.token);
+        assert_eq!(loaded.session_url, session.session_url);
+    }
+
+    #[test]
+    fn archive_sessions_by_digest() {
+        let dir = TempDir::new().unwrap();
+        let state_dir = dir.path().join("state");
+        let first = UploadSession::archive_state_file(&state_dir, dir.path(), "abc").unwrap();
+        assert_eq!(
+            first,
+            UploadSession::archive_state_file(&state_dir, dir.path(), "abc").unwrap()
+        );
+        assert_ne!(
+            first,
+            UploadSession::archive_state_file(&state_dir, dir.path(), "def").unwrap()
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use bytes::Bytes;
+use futures::future;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_util::codec;
+
+/// The content of a single file upload.
+///
+/// A source is either a file on disk, an in-memory buffer or an async reader
+/// with a known length, so files that are produced in memory can be uploaded
+/// without writing them to disk first.
+///
+/// Example
+///
+/// ```no_run
+/// use kaggle::upload::UploadSource;
+/// use kaggle::KaggleApiClient;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let kaggle = KaggleApiClient::builder().build()?;
+///     let csv = "id,label\n1,0\n2,1\n";
+///     let resp = kaggle
+///         .competition_submit_source(
+///             UploadSource::bytes("submission.csv", csv),
+///             "titanic",
+///             "in-memory submission",
+///         )
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+pub struct UploadSource {
+    name: String,
+    content_length: u64,
+    last_modified: SystemTime,
+    body: SourceBody,
+}
+
+enum SourceBody {
+    File(PathBuf),
+    Bytes(Bytes),
+    Reader(Box<dyn AsyncRead + Send + Unpin>),
+}
+
+impl UploadSource {
+    /// Upload the file at `path` under its file name.
+    pub fn file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow::anyhow!("Invalid file name {}", path.display()))?;
+        let meta = path.metadata()?;
+        Ok(Self {
+            name: name.to_string(),
+            content_length: meta.len(),
+            last_modified: meta.modified().unwrap_or_else(|_| SystemTime::now()),
+            body: SourceBody::File(path.to_path_buf()),
+        })
+    }
+
+    /// Upload the content of an in-memory buffer as file `name`.
+    pub fn bytes(name: impl ToString, content: impl Into<Bytes>) -> Self {
+        let content = content.into();
+        Self {
+            name: name.to_string(),
+            content_length: content.len() as u64,
+            last_modified: SystemTime::now(),
+            body: SourceBody::Bytes(content),
+        }
+    }
+
+    /// Upload the first `content_length` bytes of `reader` as file `name`.
+    ///
+    /// The upload fails if the reader ends before `content_length` bytes are
+    /// read.
+    pub fn reader<R>(name: impl ToString, reader: R, content_length: u64) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            content_length,
+            last_modified: SystemTime::now(),
+            body: SourceBody::Reader(Box::new(reader)),
+        }
+    }
+
+    /// Set the name of the uploaded file.
+    pub fn name(mut self, name: impl ToString) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Set the modification time that is reported for the file, defaults to
+    /// the file's modification time or the time the source was created.
+    pub fn last_modified(mut self, last_modified: SystemTime) -> Self {
+        self.last_modified = last_modified;
+        self
+    }
+
+    /// The name of the uploaded file.
+    pub fn file_name(&self) -> &str {
+        &self.name
+    }
+
+    /// The number of bytes that are uploaded.
+    pub fn content_length(&self) -> u64 {
+        self.content_length
+    }
+
+    /// The modification time as duration since the unix epoch.
+    pub(crate) fn last_modified_since_epoch(&self) -> Duration {
+        self.last_modified
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    /// The path if this source is a file on disk.
+    pub(crate) fn path(&self) -> Option<&Path> {
+        match &self.body {
+            SourceBody::File(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Turn the content into a request body.
+    pub(crate) async fn into_body(self) -> anyhow::Result<reqwest::Body> {
+        let reader: Box<dyn AsyncRead + Send + Unpin> = match self.body {
+            SourceBody::Bytes(bytes) => return Ok(bytes.into()),
+            SourceBody::File(path) => Box::new(tokio::fs::File::open(path).await?),
+            SourceBody::Reader(reader) => reader,
+        };
+        Ok(reqwest::Body::wrap_stream(exact_length(
+            reader,
+            self.content_length,
+        )))
+    }
+}
+
+/// Stream the first `expected` bytes of the reader, ending with an error if
+/// the reader is shorter.
+fn exact_length<R>(reader: R, expected: u64) -> impl Stream<Item = io::Result<Bytes>>
+where
+    R: AsyncRead + Unpin,
+{
+    let read = Arc::new(AtomicU64::new(0));
+    let counter = Arc::clone(&read);
+    // fail the upload instead of sending a truncated file
+    let eof = stream::once(async move {
+        let read = read.load(Ordering::Relaxed);
+        if read < expected {
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("Upload source ended after {} of {} bytes", read, expected),
+            ))
+        } else {
+            Ok(Bytes::new())
+        }
+    });
+    codec::FramedRead::new(reader.take(expected), codec::BytesCodec::new())
+        .map_ok(|bytes| bytes.freeze())
+        .inspect_ok(move |bytes| {
+            counter.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        })
+        .chain(eof)
+        .try_filter(|bytes| future::ready(!bytes.is_empty()))
+}
+
+impl fmt::Debug for UploadSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let body = match &self.body {
+            SourceBody::File(path) => format!("File({})", path.display()),
+            SourceBody::Bytes(_) => "Bytes".to_string(),
+            SourceBody::Reader(_) => "Reader".to_string(),
+        };
+        f.debug_struct("UploadSource")
+            .field("name", &self.name)
+            .field("content_length", &self.content_length)
+            .field("last_modified", &self.last_modified)
+            .field("body", &body)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn exact_length_reader() {
+        let content: &[u8] = b"0123456789";
+
+        let chunks: Vec<_> = exact_length(content, 4).try_collect().await.unwrap();
+        assert_eq!(chunks.concat(), b"0123");
+
+        let err = exact_length(content, 20)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}